@@ -24,6 +24,7 @@ extern crate alloc;
 #[cfg(feature = "std")]
 use serde::Serialize;
 use codec::{Encode, Decode, Codec};
+use core::convert::TryFrom;
 use sr_primitives::{ConsensusEngineId, traits::{DigestFor, NumberFor}};
 use client::decl_runtime_apis;
 use rstd::vec::Vec;
@@ -62,6 +63,66 @@ pub struct ScheduledChange<N> {
 	pub delay: N,
 }
 
+/// Current state of the GRANDPA authority set. State transitions must happen in
+/// the same order of states defined below, e.g. `Paused` implies a prior
+/// `PendingPause`.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Decode, Encode, PartialEq, Eq, Clone)]
+pub enum StoredState<N> {
+	/// The current authority set is live, and GRANDPA is enabled.
+	Live,
+	/// There is a pending pause event which will be enacted at the given block
+	/// height.
+	PendingPause {
+		/// Block at which the intention to pause was scheduled.
+		scheduled_at: N,
+		/// Number of blocks after which the change will be enacted.
+		delay: N
+	},
+	/// The current GRANDPA authority set is paused.
+	Paused,
+	/// There is a pending resume event which will be enacted at the given block
+	/// height.
+	PendingResume {
+		/// Block at which the intention to resume was scheduled.
+		scheduled_at: N,
+		/// Number of blocks after which the change will be enacted.
+		delay: N,
+	},
+}
+
+/// Conversion of a state to its discriminant, dropping any embedded fields.
+///
+/// This is only meant for checking which state the authority set is in, e.g.
+/// in storage proofs; use the SCALE encoding to store a full state.
+impl<N> From<StoredState<N>> for u8 {
+	fn from(state: StoredState<N>) -> u8 {
+		match state {
+			StoredState::Live => 0,
+			StoredState::PendingPause { .. } => 1,
+			StoredState::Paused => 2,
+			StoredState::PendingResume { .. } => 3,
+		}
+	}
+}
+
+/// Conversion of a discriminant to a state. Embedded fields are not part of
+/// the discriminant and are set to their default values, so this does not
+/// recover a full state.
+impl<N: Default> TryFrom<u8> for StoredState<N> {
+	type Error = &'static str;
+
+	fn try_from(discriminant: u8) -> Result<Self, Self::Error> {
+		match discriminant {
+			0 => Ok(StoredState::Live),
+			1 => Ok(StoredState::PendingPause { scheduled_at: N::default(), delay: N::default() }),
+			2 => Ok(StoredState::Paused),
+			3 => Ok(StoredState::PendingResume { scheduled_at: N::default(), delay: N::default() }),
+			_ => Err("Invalid GRANDPA state discriminant"),
+		}
+	}
+}
+
 /// An consensus log item for GRANDPA.
 #[cfg_attr(feature = "std", derive(Serialize, Debug))]
 #[derive(Decode, Encode, PartialEq, Eq, Clone)]
@@ -157,7 +218,7 @@ decl_runtime_apis! {
 	/// applied in the runtime after those N blocks have passed.
 	///
 	/// The consensus protocol will coordinate the handoff externally.
-	#[api_version(2)]
+	#[api_version(7)]
 	pub trait GrandpaApi {
 		/// Check a digest for pending changes.
 		/// Return `None` if there are no pending changes.
@@ -203,5 +264,50 @@ decl_runtime_apis! {
 		/// used to finalize descendants of this block (B+1, B+2, ...). The block B itself
 		/// is finalized by the authorities from block B-1.
 		fn grandpa_authorities() -> Vec<(AuthorityId, AuthorityWeight)>;
+
+		/// Check whether the given authorities and weights match the authority
+		/// set recorded for the given set id. Returns `false` for unknown set ids.
+		///
+		/// Added in version 3.
+		fn grandpa_verify_set_for_id(
+			set_id: u64,
+			authorities: Vec<(AuthorityId, AuthorityWeight)>,
+		) -> bool;
+
+		/// Get the id of the current GRANDPA authority set, i.e. the set returned
+		/// by `grandpa_authorities`. The genesis set has id `0` and every enacted
+		/// change increments it by one.
+		///
+		/// Added in version 4.
+		fn grandpa_current_set_id() -> u64;
+
+		/// Get the minimum number of current authorities that must be online
+		/// for the set to reach a supermajority and finalize blocks.
+		///
+		/// Added in version 5.
+		fn grandpa_min_validators_for_finality() -> u32;
+
+		/// Compare a proposed authority set against the current one, returning
+		/// the added authorities, the removed authorities and the authorities
+		/// whose weight changes, with their current and proposed weights.
+		///
+		/// Added in version 6.
+		fn grandpa_diff_authorities(proposed: Vec<(AuthorityId, AuthorityWeight)>) -> (
+			Vec<AuthorityId>,
+			Vec<AuthorityId>,
+			Vec<(AuthorityId, AuthorityWeight, AuthorityWeight)>,
+		);
+
+		/// Get the id of the current GRANDPA authority set together with its
+		/// authorities and weights, as read from the same state.
+		///
+		/// Added in version 7.
+		fn grandpa_authority_set() -> (u64, Vec<(AuthorityId, AuthorityWeight)>);
+	}
+
+	/// APIs for querying whether the GRANDPA authority set is live or paused.
+	pub trait GrandpaStateApi {
+		/// Get the current state of the GRANDPA authority set.
+		fn grandpa_state() -> StoredState<NumberFor<Block>>;
 	}
 }
@@ -310,6 +310,56 @@ impl GrandpaApi<Block> for RuntimeApi {
 		// extrinsics.
 		Ok(self.inner.forced_changes.lock().get(&parent_hash).map(|c| c.clone())).map(NativeOrEncoded::Native)
 	}
+
+	fn GrandpaApi_grandpa_verify_set_for_id_runtime_api_impl(
+		&self,
+		_: &BlockId<Block>,
+		_: ExecutionContext,
+		_: Option<(u64, Vec<(AuthorityId, u64)>)>,
+		_: Vec<u8>,
+	) -> Result<NativeOrEncoded<bool>> {
+		unimplemented!("Not required for testing!")
+	}
+
+	fn GrandpaApi_grandpa_current_set_id_runtime_api_impl(
+		&self,
+		_: &BlockId<Block>,
+		_: ExecutionContext,
+		_: Option<()>,
+		_: Vec<u8>,
+	) -> Result<NativeOrEncoded<u64>> {
+		unimplemented!("Not required for testing!")
+	}
+
+	fn GrandpaApi_grandpa_min_validators_for_finality_runtime_api_impl(
+		&self,
+		_: &BlockId<Block>,
+		_: ExecutionContext,
+		_: Option<()>,
+		_: Vec<u8>,
+	) -> Result<NativeOrEncoded<u32>> {
+		unimplemented!("Not required for testing!")
+	}
+
+	fn GrandpaApi_grandpa_diff_authorities_runtime_api_impl(
+		&self,
+		_: &BlockId<Block>,
+		_: ExecutionContext,
+		_: Option<(Vec<(AuthorityId, u64)>)>,
+		_: Vec<u8>,
+	) -> Result<NativeOrEncoded<(Vec<AuthorityId>, Vec<AuthorityId>, Vec<(AuthorityId, u64, u64)>)>> {
+		unimplemented!("Not required for testing!")
+	}
+
+	fn GrandpaApi_grandpa_authority_set_runtime_api_impl(
+		&self,
+		_: &BlockId<Block>,
+		_: ExecutionContext,
+		_: Option<()>,
+		_: Vec<u8>,
+	) -> Result<NativeOrEncoded<(u64, Vec<(AuthorityId, u64)>)>> {
+		unimplemented!("Not required for testing!")
+	}
 }
 
 impl AuthoritySetForFinalityProver<Block> for TestApi {
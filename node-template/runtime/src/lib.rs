@@ -191,8 +191,27 @@ impl babe::Trait for Runtime {
 	type ExpectedBlockTime = ExpectedBlockTime;
 }
 
+parameter_types! {
+	pub const MaxPendingChanges: u32 = 1;
+	pub const MaxAuthorities: u32 = 100;
+	pub const ForcedChangeOverridesPending: bool = true;
+	pub const AuthoritySetHistoryDepth: u32 = 256;
+	pub const AuthorityRemovalDelay: BlockNumber = 10;
+	pub const InitialSetId: u64 = 0;
+}
+
 impl grandpa::Trait for Runtime {
 	type Event = Event;
+	type MaxPendingChanges = MaxPendingChanges;
+	type MaxAuthorities = MaxAuthorities;
+	type ForcedChangeOverridesPending = ForcedChangeOverridesPending;
+	type AuthoritySetHistoryDepth = AuthoritySetHistoryDepth;
+	type PauseOrigin = system::EnsureRoot<AccountId>;
+	type ResumeOrigin = system::EnsureRoot<AccountId>;
+	type AuthorityRemovalDelay = AuthorityRemovalDelay;
+	type ForceOrigin = system::EnsureRoot<AccountId>;
+	type CancelOrigin = system::EnsureRoot<AccountId>;
+	type InitialSetId = InitialSetId;
 }
 
 impl indices::Trait for Runtime {
@@ -266,7 +285,7 @@ construct_runtime!(
 		System: system::{Module, Call, Storage, Config, Event},
 		Timestamp: timestamp::{Module, Call, Storage, Inherent},
 		Babe: babe::{Module, Call, Storage, Config, Inherent(Timestamp)},
-		Grandpa: grandpa::{Module, Call, Storage, Config, Event},
+		Grandpa: grandpa::{Module, Call, Storage, Config, Event<T>},
 		Indices: indices::{default, Config<T>},
 		Balances: balances,
 		Sudo: sudo,
@@ -372,6 +391,39 @@ impl_runtime_apis! {
 		fn grandpa_authorities() -> Vec<(GrandpaId, GrandpaWeight)> {
 			Grandpa::grandpa_authorities()
 		}
+
+		fn grandpa_verify_set_for_id(
+			set_id: u64,
+			authorities: Vec<(GrandpaId, GrandpaWeight)>,
+		) -> bool {
+			Grandpa::verify_set_for_id(set_id, &authorities)
+		}
+
+		fn grandpa_current_set_id() -> u64 {
+			Grandpa::current_set_id()
+		}
+
+		fn grandpa_min_validators_for_finality() -> u32 {
+			Grandpa::min_validators_for_finality()
+		}
+
+		fn grandpa_diff_authorities(proposed: Vec<(GrandpaId, GrandpaWeight)>) -> (
+			Vec<GrandpaId>,
+			Vec<GrandpaId>,
+			Vec<(GrandpaId, GrandpaWeight, GrandpaWeight)>,
+		) {
+			Grandpa::diff_authorities(&proposed)
+		}
+
+		fn grandpa_authority_set() -> (u64, Vec<(GrandpaId, GrandpaWeight)>) {
+			Grandpa::authority_set()
+		}
+	}
+
+	impl fg_primitives::GrandpaStateApi<Block> for Runtime {
+		fn grandpa_state() -> fg_primitives::StoredState<NumberFor<Block>> {
+			Grandpa::state()
+		}
 	}
 
 	impl babe_primitives::BabeApi<Block> for Runtime {
@@ -37,7 +37,7 @@ native_executor_instance!(
 #[cfg(test)]
 mod tests {
 	use super::Executor;
-	use {balances, contracts, indices, system, timestamp};
+	use {balances, contracts, grandpa, indices, system, timestamp};
 	use runtime_io;
 	use substrate_executor::WasmExecutor;
 	use codec::{Encode, Decode, Joiner};
@@ -54,7 +54,7 @@ mod tests {
 	use node_primitives::{Hash, BlockNumber, Balance};
 	use node_runtime::{
 		Header, Block, UncheckedExtrinsic, CheckedExtrinsic, Call, Runtime, Balances, BuildStorage,
-		System, Event,
+		System, Event, Grandpa,
 		TransferFee, TransactionBaseFee, TransactionByteFee,
 	};
 	use node_runtime::constants::currency::*;
@@ -1122,6 +1122,56 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn grandpa_api_reads_module_storage() {
+		type Authorities = Vec<(grandpa::AuthorityId, grandpa::AuthorityWeight)>;
+
+		let mut t = new_test_ext(COMPACT_CODE, false);
+		let (set_id, authorities, min_validators) = runtime_io::with_externalities(&mut t, || (
+			Grandpa::current_set_id(),
+			Grandpa::grandpa_authorities(),
+			Grandpa::min_validators_for_finality(),
+		));
+		// the genesis session keys provide the grandpa authorities.
+		assert!(!authorities.is_empty());
+
+		// call the runtime api both natively and in wasm, the results must agree.
+		let mut call = |method: &str, data: &[u8]| -> Vec<u8> {
+			let native = executor().call::<_, NeverNativeValue, fn() -> _>(
+				&mut t,
+				method,
+				data,
+				true,
+				None,
+			).0.unwrap().as_encoded();
+			let wasm = WasmExecutor::new().call(&mut t, 8, COMPACT_CODE, method, data).unwrap();
+			assert_eq!(native, wasm, "native and wasm results differ for {}", method);
+			native
+		};
+
+		let r = call("GrandpaApi_grandpa_current_set_id", &[]);
+		assert_eq!(u64::decode(&mut &r[..]).unwrap(), set_id);
+
+		let r = call("GrandpaApi_grandpa_authority_set", &[]);
+		assert_eq!(<(u64, Authorities)>::decode(&mut &r[..]).unwrap(), (set_id, authorities.clone()));
+
+		let r = call("GrandpaApi_grandpa_verify_set_for_id", &(set_id, authorities.clone()).encode());
+		assert_eq!(bool::decode(&mut &r[..]).unwrap(), true);
+		let r = call("GrandpaApi_grandpa_verify_set_for_id", &(set_id + 1, authorities.clone()).encode());
+		assert_eq!(bool::decode(&mut &r[..]).unwrap(), false);
+
+		let r = call("GrandpaApi_grandpa_min_validators_for_finality", &[]);
+		assert_eq!(u32::decode(&mut &r[..]).unwrap(), min_validators);
+
+		let r = call("GrandpaApi_grandpa_diff_authorities", &authorities.encode());
+		let (added, removed, changed) = <(
+			Vec<grandpa::AuthorityId>,
+			Vec<grandpa::AuthorityId>,
+			Vec<(grandpa::AuthorityId, grandpa::AuthorityWeight, grandpa::AuthorityWeight)>,
+		)>::decode(&mut &r[..]).unwrap();
+		assert!(added.is_empty() && removed.is_empty() && changed.is_empty());
+	}
+
 	#[cfg(feature = "benchmarks")]
 	mod benches {
 		use super::*;
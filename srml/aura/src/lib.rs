@@ -28,6 +28,9 @@
 //! ### Public Functions
 //!
 //! - `slot_duration` - Determine the Aura slot-duration based on the Timestamp module configuration.
+//! - `blocks_authored` - Get the number of blocks authored by an authority.
+//! - `current_slot` - Get the slot of the current block.
+//! - `slot_author` - Get the authority expected to author in a given slot.
 //!
 //! ## Related Modules
 //!
@@ -50,7 +53,7 @@ pub use timestamp;
 use rstd::{result, prelude::*};
 use codec::{Encode, Decode};
 use srml_support::{
-	decl_storage, decl_module, Parameter, storage::StorageValue, traits::{Get, FindAuthor},
+	decl_storage, decl_module, Parameter, storage::{StorageValue, StorageMap}, traits::{Get, FindAuthor},
 	ConsensusEngineId,
 };
 use app_crypto::AppPublic;
@@ -152,6 +155,13 @@ decl_storage! {
 
 		/// The current authorities
 		pub Authorities get(authorities): Vec<T::AuthorityId>;
+
+		/// The slot of the current block, as derived from its timestamp.
+		CurrentSlot get(current_slot): u64;
+
+		/// The number of blocks authored by each current authority. Entries are
+		/// removed when an authority leaves the set.
+		AuthoredBlocks get(blocks_authored): map T::AuthorityId => u32;
 	}
 	add_extra_genesis {
 		config(authorities): Vec<T::AuthorityId>;
@@ -168,11 +178,18 @@ decl_storage! {
 }
 
 decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin { }
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn on_initialize() {
+			Self::note_author();
+		}
+	}
 }
 
 impl<T: Trait> Module<T> {
 	fn change_authorities(new: Vec<T::AuthorityId>) {
+		for old in Self::authorities().iter().filter(|old| !new.contains(old)) {
+			<AuthoredBlocks<T>>::remove(old);
+		}
 		<Authorities<T>>::put(&new);
 
 		let log: DigestItem<T::Hash> = DigestItem::Consensus(
@@ -182,6 +199,23 @@ impl<T: Trait> Module<T> {
 		<system::Module<T>>::deposit_log(log.into());
 	}
 
+	/// Count the current block towards the authority that authored it, as
+	/// found in the pre-runtime digest.
+	fn note_author() {
+		let authorities = Self::authorities();
+		if authorities.is_empty() {
+			return;
+		}
+
+		let digest = <system::Module<T>>::digest();
+		let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+		if let Some(author) = Self::find_author(pre_runtime_digests) {
+			if let Some(id) = authorities.get(author as usize) {
+				<AuthoredBlocks<T>>::mutate(id, |count| *count = count.saturating_add(1));
+			}
+		}
+	}
+
 	fn initialize_authorities(authorities: &[T::AuthorityId]) {
 		if !authorities.is_empty() {
 			assert!(<Authorities<T>>::get().is_empty(), "Authorities are already initialized!");
@@ -256,18 +290,30 @@ impl<T: Trait> Module<T> {
 		<T as timestamp::Trait>::MinimumPeriod::get().saturating_mul(2.into())
 	}
 
+	/// Get the authority whose turn it is to author in the given slot.
+	pub fn slot_author(slot: u64) -> Option<T::AuthorityId> {
+		let authorities = Self::authorities();
+		if authorities.is_empty() {
+			return None;
+		}
+
+		authorities.get((slot % authorities.len() as u64) as usize).cloned()
+	}
+
 	fn on_timestamp_set(now: T::Moment, slot_duration: T::Moment) {
 		let last = Self::last();
 		<Self as Store>::LastTimestamp::put(now.clone());
 
+		assert!(!slot_duration.is_zero(), "Aura slot duration cannot be zero.");
+
+		let cur_slot = now / slot_duration.clone();
+		<Self as Store>::CurrentSlot::put(cur_slot.clone().saturated_into::<u64>());
+
 		if last.is_zero() {
 			return;
 		}
 
-		assert!(!slot_duration.is_zero(), "Aura slot duration cannot be zero.");
-
-		let last_slot = last / slot_duration.clone();
-		let cur_slot = now / slot_duration;
+		let last_slot = last / slot_duration;
 
 		assert!(last_slot < cur_slot, "Only one block may be authored per slot.");
 
@@ -81,4 +81,5 @@ pub fn new_test_ext(authorities: Vec<u64>) -> runtime_io::TestExternalities<Blak
 	t.into()
 }
 
+pub type System = system::Module<Test>;
 pub type Aura = Module<Test>;
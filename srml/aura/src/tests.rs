@@ -19,7 +19,11 @@
 #![cfg(test)]
 
 use runtime_io::with_externalities;
-use crate::mock::{Aura, new_test_ext};
+use sr_primitives::{testing::{Digest, UintAuthorityId}, traits::OnInitialize, generic::DigestItem};
+use codec::Encode;
+use substrate_consensus_aura_primitives::{AURA_ENGINE_ID, ed25519::AuthorityId};
+use session::OneSessionHandler;
+use crate::mock::{System, Aura, new_test_ext};
 
 #[test]
 fn initial_values() {
@@ -28,3 +32,77 @@ fn initial_values() {
 		assert_eq!(Aura::authorities().len(), 4);
 	});
 }
+
+#[test]
+fn current_slot_follows_timestamp() {
+	with_externalities(&mut new_test_ext(vec![0, 1, 2, 3]), || {
+		let slot_duration = Aura::slot_duration();
+
+		Aura::on_timestamp_set(5 * slot_duration, slot_duration);
+		assert_eq!(Aura::current_slot(), 5);
+
+		Aura::on_timestamp_set(7 * slot_duration + 1, slot_duration);
+		assert_eq!(Aura::current_slot(), 7);
+	});
+}
+
+#[test]
+fn slot_author_rotates_through_authorities() {
+	with_externalities(&mut new_test_ext(vec![0, 1, 2, 3]), || {
+		let authorities = Aura::authorities();
+		for slot in 0..8 {
+			assert_eq!(Aura::slot_author(slot), Some(authorities[slot as usize % 4].clone()));
+		}
+	});
+
+	with_externalities(&mut new_test_ext(vec![]), || {
+		assert_eq!(Aura::slot_author(0), None);
+	});
+}
+
+#[test]
+fn authored_blocks_are_counted() {
+	with_externalities(&mut new_test_ext(vec![0, 1, 2, 3]), || {
+		// authorities are assigned slots round-robin.
+		for (number, slot) in [1u64, 2, 5, 6, 7].iter().enumerate() {
+			let digest = Digest {
+				logs: vec![DigestItem::PreRuntime(AURA_ENGINE_ID, slot.encode())],
+			};
+			let number = number as u64 + 1;
+			System::initialize(&number, &Default::default(), &Default::default(), &digest);
+			Aura::on_initialize(number);
+		}
+
+		let blocks_authored = |id: u64| Aura::blocks_authored(UintAuthorityId(id).to_public_key::<AuthorityId>());
+		assert_eq!(blocks_authored(0), 0);
+		assert_eq!(blocks_authored(1), 2);
+		assert_eq!(blocks_authored(2), 2);
+		assert_eq!(blocks_authored(3), 1);
+	});
+}
+
+#[test]
+fn authored_blocks_are_pruned_when_authorities_leave() {
+	with_externalities(&mut new_test_ext(vec![0, 1, 2, 3]), || {
+		// slots 1, 2 and 3 are authored by authorities 1, 2 and 3.
+		for (number, slot) in [1u64, 2, 3].iter().enumerate() {
+			let digest = Digest {
+				logs: vec![DigestItem::PreRuntime(AURA_ENGINE_ID, slot.encode())],
+			};
+			let number = number as u64 + 1;
+			System::initialize(&number, &Default::default(), &Default::default(), &digest);
+			Aura::on_initialize(number);
+		}
+
+		let to_id = |id: u64| UintAuthorityId(id).to_public_key::<AuthorityId>();
+		let accounts = vec![2u64, 3, 4];
+		let validators = accounts.iter().map(|id| (id, to_id(*id))).collect::<Vec<_>>();
+		Aura::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+
+		assert_eq!(Aura::authorities(), vec![to_id(2), to_id(3), to_id(4)]);
+		assert_eq!(Aura::blocks_authored(to_id(1)), 0);
+		assert_eq!(Aura::blocks_authored(to_id(2)), 1);
+		assert_eq!(Aura::blocks_authored(to_id(3)), 1);
+		assert_eq!(Aura::blocks_authored(to_id(4)), 0);
+	});
+}
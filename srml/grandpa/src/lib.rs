@@ -24,35 +24,81 @@
 //!
 //! For full integration with GRANDPA, the `GrandpaApi` should be implemented.
 //! The necessary items are re-exported via the `fg_primitives` crate.
+//!
+//! Authority set changes and pauses never overlap: a change can only be
+//! scheduled while the set is live, and a pause cannot be scheduled while a
+//! change is pending. A change is therefore always enacted on a live set, and
+//! any change requested by a new session while paused is dropped.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 // re-export since this is necessary for `impl_apis` in runtime.
 pub use substrate_finality_grandpa_primitives as fg_primitives;
 
-use rstd::prelude::*;
+use rstd::{prelude::*, collections::btree_set::BTreeSet};
 use codec::{self as codec, Encode, Decode, Error};
 use srml_support::{
-	decl_event, decl_storage, decl_module, dispatch::Result, storage::StorageValue
+	decl_event, decl_storage, decl_module, dispatch::Result,
+	storage::{StorageValue, StorageMap}, traits::Get,
 };
 use sr_primitives::{
-	generic::{DigestItem, OpaqueDigestItemId}, traits::Zero,
-	Perbill,
+	generic::{DigestItem, OpaqueDigestItemId}, traits::{Zero, One, Saturating, CheckedAdd, EnsureOrigin},
+	Perbill, weights::SimpleDispatchInfo,
 };
 use sr_staking_primitives::{
 	SessionIndex,
 	offence::{Offence, Kind},
 };
 use fg_primitives::{ScheduledChange, ConsensusLog, GRANDPA_ENGINE_ID};
-pub use fg_primitives::{AuthorityId, AuthorityWeight};
-use system::{ensure_signed, DigestOf};
+pub use fg_primitives::{AuthorityId, AuthorityWeight, StoredState};
+use system::{ensure_signed, ensure_root, DigestOf};
 
 mod mock;
 mod tests;
+mod weights;
+
+/// Bit of `ActiveFlags` set while `PendingChanges` may be non-empty.
+pub const PENDING_CHANGES_FLAG: u8 = 1 << 0;
+/// Bit of `ActiveFlags` set while `State` may be a pending pause or resume.
+pub const PENDING_STATE_FLAG: u8 = 1 << 1;
+/// Bit of `ActiveFlags` set until the one-off storage migrations have run.
+pub const MIGRATIONS_FLAG: u8 = 1 << 2;
 
 pub trait Trait: system::Trait {
 	/// The event type of this module.
-	type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+	/// The maximum number of authority set changes that can be queued at once.
+	type MaxPendingChanges: Get<u32>;
+
+	/// The maximum number of authorities in a GRANDPA authority set.
+	type MaxAuthorities: Get<u32>;
+
+	/// Whether scheduling a forced change drops queued regular changes that
+	/// have not been signaled yet instead of queueing behind them.
+	type ForcedChangeOverridesPending: Get<bool>;
+
+	/// The number of most recent authority sets kept in `HistoricalAuthorities`.
+	type AuthoritySetHistoryDepth: Get<u32>;
+
+	/// The origin allowed to schedule a pause of GRANDPA.
+	type PauseOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The origin allowed to schedule a resume of GRANDPA.
+	type ResumeOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The delay after which a change removing a single authority is enacted.
+	type AuthorityRemovalDelay: Get<Self::BlockNumber>;
+
+	/// The origin allowed to note a stall of finality.
+	type ForceOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The origin allowed to cancel queued authority set changes.
+	type CancelOrigin: EnsureOrigin<Self::Origin>;
+
+	/// The set id native voters are at when this module is upgraded on a chain
+	/// that did not track set ids from genesis. Used once to seed `CurrentSetId`.
+	type InitialSetId: Get<u64>;
 }
 
 /// A stored pending change, old format.
@@ -96,42 +142,31 @@ impl<N: Decode> Decode for StoredPendingChange<N> {
 	}
 }
 
-/// Current state of the GRANDPA authority set. State transitions must happen in
-/// the same order of states defined below, e.g. `Paused` implies a prior
-/// `PendingPause`.
-#[derive(Decode, Encode)]
-#[cfg_attr(test, derive(Debug, PartialEq))]
-pub enum StoredState<N> {
-	/// The current authority set is live, and GRANDPA is enabled.
-	Live,
-	/// There is a pending pause event which will be enacted at the given block
-	/// height.
-	PendingPause {
-		/// Block at which the intention to pause was scheduled.
-		scheduled_at: N,
-		/// Number of blocks after which the change will be enacted.
-		delay: N
-	},
-	/// The current GRANDPA authority set is paused.
-	Paused,
-	/// There is a pending resume event which will be enacted at the given block
-	/// height.
-	PendingResume {
-		/// Block at which the intention to resume was scheduled.
-		scheduled_at: N,
-		/// Number of blocks after which the change will be enacted.
-		delay: N,
-	},
-}
-
 decl_event!(
-	pub enum Event {
-		/// New authority set has been applied.
-		NewAuthorities(Vec<(AuthorityId, u64)>),
+	pub enum Event<T> where BlockNumber = <T as system::Trait>::BlockNumber {
+		/// New authority set has been applied, along with its set id.
+		NewAuthorities(u64, Vec<(AuthorityId, u64)>),
 		/// Current authority set has been paused.
 		Paused,
 		/// Current authority set has been resumed.
 		Resumed,
+		/// A forced change dropped the given number of queued regular changes.
+		PendingChangesOverridden(u32),
+		/// Finality has stalled; a forced change will be scheduled at the next
+		/// session change, with the given delay and median finalized block.
+		Stalled(BlockNumber, BlockNumber),
+		/// A forced change to the given authorities has been signaled, along
+		/// with the median last finalized block it builds on.
+		ForcedChangeSignaled(BlockNumber, Vec<(AuthorityId, u64)>),
+		/// All queued authority set changes have been canceled.
+		ScheduledChangeCanceled,
+		/// Current authority set has been paused at the given block.
+		PausedAt(BlockNumber),
+		/// Current authority set has been resumed at the given block.
+		ResumedAt(BlockNumber),
+		/// The authorities of a new session could not be scheduled, so the
+		/// current set stays in force.
+		SessionChangeRejected,
 	}
 );
 
@@ -143,14 +178,48 @@ decl_storage! {
 		/// State of the current authority set.
 		State get(state): StoredState<T::BlockNumber> = StoredState::Live;
 
-		/// Pending change: (signaled at, scheduled change).
+		/// Deprecated single pending change, migrated into `PendingChanges` on
+		/// block initialization.
 		PendingChange: Option<StoredPendingChange<T::BlockNumber>>;
 
+		/// Queued authority set changes, ordered by enactment block.
+		PendingChanges get(pending_changes): Vec<StoredPendingChange<T::BlockNumber>>;
+
+		/// Bitmask of the work `on_finalize` may have to do, so that idle blocks
+		/// can skip reading `PendingChanges` and `State`. Starts with all bits set
+		/// so that the first block after an upgrade checks everything once.
+		ActiveFlags get(active_flags): u8 = u8::max_value();
+
 		/// next block number where we can force a change.
 		NextForced get(next_forced): Option<T::BlockNumber>;
 
 		/// `true` if we are currently stalled.
 		Stalled get(stalled): Option<(T::BlockNumber, T::BlockNumber)>;
+
+		/// The median last finalized block of the most recently scheduled forced
+		/// change, i.e. the block the new voter should start from.
+		LastForcedMedian get(last_forced_median): Option<T::BlockNumber>;
+
+		/// The number of changes (both in terms of keys and underlying economic responsibilities)
+		/// in the "set" of Grandpa validators from genesis. Seeded from `InitialSetId`
+		/// on chains that upgraded to this module after genesis.
+		CurrentSetId get(current_set_id): u64;
+
+		/// The first set id tracked by this module: zero for chains tracking set
+		/// ids from genesis, otherwise the seeded `InitialSetId`.
+		FirstSetId get(first_set_id): u64;
+
+		/// The authority set recorded for each set id, starting with the genesis set.
+		/// Only the last `AuthoritySetHistoryDepth` sets are kept.
+		HistoricalAuthorities get(historical_authorities): map u64 => Option<Vec<(AuthorityId, AuthorityWeight)>>;
+
+		/// The first session in which each set id was in force at a session change.
+		/// Pruned along with `HistoricalAuthorities`.
+		SetIdSession get(session_for_set_id): map u64 => Option<SessionIndex>;
+
+		/// The block at the end of which each set id became active, starting with
+		/// the genesis set at block zero. Pruned along with `HistoricalAuthorities`.
+		SetIdActivationBlock get(set_activation_block): map u64 => Option<T::BlockNumber>;
 	}
 	add_extra_genesis {
 		config(authorities): Vec<(AuthorityId, AuthorityWeight)>;
@@ -168,49 +237,190 @@ decl_storage! {
 
 decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		/// The maximum number of authority set changes that can be queued at once.
+		const MaxPendingChanges: u32 = T::MaxPendingChanges::get();
+
+		/// The maximum number of authorities in a GRANDPA authority set.
+		const MaxAuthorities: u32 = T::MaxAuthorities::get();
+
+		/// The number of most recent authority sets kept in `HistoricalAuthorities`.
+		const AuthoritySetHistoryDepth: u32 = T::AuthoritySetHistoryDepth::get();
+
+		/// The delay after which a change removing a single authority is enacted.
+		const AuthorityRemovalDelay: T::BlockNumber = T::AuthorityRemovalDelay::get();
+
 		fn deposit_event() = default;
 
 		/// Report some misbehavior.
+		#[weight = SimpleDispatchInfo::FixedNormal(1_000_000)]
 		fn report_misbehavior(origin, _report: Vec<u8>) {
 			ensure_signed(origin)?;
 			// FIXME: https://github.com/paritytech/substrate/issues/1112
 		}
 
+		/// Cancel all queued authority set changes.
+		///
+		/// Must be called by `CancelOrigin`. Fails if any queued change is
+		/// forced or has already been signaled, since native voters act upon
+		/// those regardless of the runtime and the sets would diverge.
+		#[weight = SimpleDispatchInfo::FixedOperational(500_000)]
+		fn cancel_scheduled_change(origin) {
+			T::CancelOrigin::ensure_origin(origin)?;
+
+			let pending_changes = <PendingChanges<T>>::get();
+			if pending_changes.is_empty() {
+				return Err("No authority set change is pending");
+			}
+
+			if pending_changes.iter().any(|change| change.forced.is_some()) {
+				return Err("Cannot cancel a forced change");
+			}
+
+			// native voters enact a change once its digest has been deposited.
+			let now = <system::Module<T>>::block_number();
+			if pending_changes.iter().any(|change| change.scheduled_at < now) {
+				return Err("Cannot cancel a change that has already been signaled");
+			}
+
+			<PendingChanges<T>>::kill();
+			ActiveFlags::mutate(|flags| *flags &= !PENDING_CHANGES_FLAG);
+			Self::deposit_event(RawEvent::ScheduledChangeCanceled);
+		}
+
+		/// Schedule GRANDPA to pause starting in the given number of blocks.
+		///
+		/// Must be called by `PauseOrigin`.
+		#[weight = SimpleDispatchInfo::FixedOperational(500_000)]
+		fn pause(origin, in_blocks: T::BlockNumber) {
+			T::PauseOrigin::ensure_origin(origin)?;
+			Self::schedule_pause(in_blocks)?;
+		}
+
+		/// Schedule a resume of GRANDPA after pausing, in the given number of blocks.
+		///
+		/// Must be called by `ResumeOrigin`.
+		#[weight = SimpleDispatchInfo::FixedOperational(500_000)]
+		fn resume(origin, in_blocks: T::BlockNumber) {
+			T::ResumeOrigin::ensure_origin(origin)?;
+			Self::schedule_resume(in_blocks)?;
+		}
+
+		/// Schedule a change removing `who` from the authority set, enacted after
+		/// `AuthorityRemovalDelay` blocks.
+		///
+		/// The removal applies to the last queued change if there is one, so
+		/// that queued rotations are kept.
+		#[weight = SimpleDispatchInfo::FixedOperational(500_000)]
+		fn remove_authority(origin, who: AuthorityId) {
+			ensure_root(origin)?;
+
+			let mut authorities = Self::pending_changes().last()
+				.map(|change| change.next_authorities.clone())
+				.unwrap_or_else(Authorities::get);
+			let position = authorities.iter()
+				.position(|(id, _)| *id == who)
+				.ok_or("Authority is not in the latest scheduled set")?;
+
+			if authorities.len() == 1 {
+				return Err("Cannot remove the last authority");
+			}
+
+			authorities.remove(position);
+			Self::schedule_change(authorities, T::AuthorityRemovalDelay::get(), None)?;
+		}
+
+		/// Note that finality has stalled, so that a forced change is scheduled
+		/// at the next session change. This has the same effect as a stall
+		/// reported by the finality tracker.
+		///
+		/// Must be called by `ForceOrigin`.
+		#[weight = SimpleDispatchInfo::FixedOperational(500_000)]
+		fn note_stalled(origin, further_wait: T::BlockNumber, median: T::BlockNumber) {
+			T::ForceOrigin::ensure_origin(origin)?;
+
+			if <PendingChanges<T>>::get().iter().any(|change| change.forced.is_some()) {
+				return Err("Cannot note a stall while a forced change is pending");
+			}
+
+			<Self as finality_tracker::OnFinalizationStalled<_>>::on_stalled(further_wait, median);
+		}
+
+		fn on_initialize() {
+			if Self::active_flags() & MIGRATIONS_FLAG != 0 {
+				Self::migrate();
+			}
+		}
+
 		fn on_finalize(block_number: T::BlockNumber) {
+			let active_flags = Self::active_flags();
+			let mut flags = active_flags;
+
 			// check for scheduled pending authority set changes
-			if let Some(pending_change) = <PendingChange<T>>::get() {
-				// emit signal if we're at the block that scheduled the change
-				if block_number == pending_change.scheduled_at {
-					if let Some(median) = pending_change.forced {
-						Self::deposit_log(ConsensusLog::ForcedChange(
-							median,
-							ScheduledChange {
-								delay: pending_change.delay,
-								next_authorities: pending_change.next_authorities.clone(),
-							}
-						))
-					} else {
-						Self::deposit_log(ConsensusLog::ScheduledChange(
-							ScheduledChange{
-								delay: pending_change.delay,
-								next_authorities: pending_change.next_authorities.clone(),
-							}
-						));
+			let mut pending_changes = if flags & PENDING_CHANGES_FLAG != 0 {
+				<PendingChanges<T>>::get()
+			} else {
+				Vec::new()
+			};
+			if pending_changes.is_empty() {
+				flags &= !PENDING_CHANGES_FLAG;
+			} else {
+				for pending_change in &pending_changes {
+					// emit signal if we're at the block that scheduled the change
+					if block_number == pending_change.scheduled_at {
+						if let Some(median) = pending_change.forced {
+							Self::deposit_log(ConsensusLog::ForcedChange(
+								median,
+								ScheduledChange {
+									delay: pending_change.delay,
+									next_authorities: pending_change.next_authorities.clone(),
+								}
+							));
+							Self::deposit_event(RawEvent::ForcedChangeSignaled(
+								median,
+								pending_change.next_authorities.clone(),
+							));
+						} else {
+							Self::deposit_log(ConsensusLog::ScheduledChange(
+								ScheduledChange{
+									delay: pending_change.delay,
+									next_authorities: pending_change.next_authorities.clone(),
+								}
+							));
+						}
 					}
 				}
 
-				// enact the change if we've reached the enacting block
-				if block_number == pending_change.scheduled_at + pending_change.delay {
-					Authorities::put(&pending_change.next_authorities);
-					Self::deposit_event(
-						Event::NewAuthorities(pending_change.next_authorities)
-					);
-					<PendingChange<T>>::kill();
+				// enact all changes whose enacting block has been reached, in order
+				let enacted = pending_changes.iter()
+					.take_while(|change| change.scheduled_at + change.delay <= block_number)
+					.count();
+
+				if enacted > 0 {
+					for pending_change in pending_changes.drain(..enacted) {
+						let set_id = CurrentSetId::mutate(|id| { *id += 1; *id });
+						Authorities::put(&pending_change.next_authorities);
+						Self::record_authority_set(set_id, &pending_change.next_authorities, block_number);
+						Self::deposit_event(
+							RawEvent::NewAuthorities(set_id, pending_change.next_authorities)
+						);
+					}
+
+					if pending_changes.is_empty() {
+						<PendingChanges<T>>::kill();
+						flags &= !PENDING_CHANGES_FLAG;
+					} else {
+						<PendingChanges<T>>::put(pending_changes);
+					}
 				}
 			}
 
 			// check for scheduled pending state changes
-			match <State<T>>::get() {
+			let state = if flags & PENDING_STATE_FLAG != 0 {
+				<State<T>>::get()
+			} else {
+				StoredState::Live
+			};
+			match state {
 				StoredState::PendingPause { scheduled_at, delay } => {
 					// signal change to pause
 					if block_number == scheduled_at {
@@ -220,7 +430,9 @@ decl_module! {
 					// enact change to paused state
 					if block_number == scheduled_at + delay {
 						<State<T>>::put(StoredState::Paused);
-						Self::deposit_event(Event::Paused);
+						flags &= !PENDING_STATE_FLAG;
+						Self::deposit_event(RawEvent::Paused);
+						Self::deposit_event(RawEvent::PausedAt(block_number));
 					}
 				},
 				StoredState::PendingResume { scheduled_at, delay } => {
@@ -232,10 +444,16 @@ decl_module! {
 					// enact change to live state
 					if block_number == scheduled_at + delay {
 						<State<T>>::put(StoredState::Live);
-						Self::deposit_event(Event::Resumed);
+						flags &= !PENDING_STATE_FLAG;
+						Self::deposit_event(RawEvent::Resumed);
+						Self::deposit_event(RawEvent::ResumedAt(block_number));
 					}
 				},
-				_ => {},
+				_ => flags &= !PENDING_STATE_FLAG,
+			}
+
+			if flags != active_flags {
+				ActiveFlags::put(flags);
 			}
 		}
 	}
@@ -247,13 +465,145 @@ impl<T: Trait> Module<T> {
 		Authorities::get()
 	}
 
+	/// Get the current set id together with the current authorities, so that
+	/// both are read from the same state.
+	pub fn authority_set() -> (u64, Vec<(AuthorityId, AuthorityWeight)>) {
+		(Self::current_set_id(), Authorities::get())
+	}
+
+	/// Get the authorities, along with their weights, that made up the set
+	/// with the given id.
+	///
+	/// Returns `None` if no such set exists yet or it has already been pruned,
+	/// see `oldest_available_set_id`.
+	pub fn authorities_at(set_id: u64) -> Option<Vec<(AuthorityId, AuthorityWeight)>> {
+		Self::historical_authorities(set_id)
+	}
+
+	/// The oldest set id whose authorities are still available through
+	/// `authorities_at`. Older sets have been pruned.
+	pub fn oldest_available_set_id() -> u64 {
+		let depth = rstd::cmp::max(T::AuthoritySetHistoryDepth::get(), 1);
+		let oldest = Self::current_set_id().saturating_sub(depth as u64 - 1);
+		rstd::cmp::max(oldest, Self::first_set_id())
+	}
+
+	/// Check whether the given authorities match the set recorded for `set_id`.
+	///
+	/// Returns `false` if no set was recorded for that id.
+	pub fn verify_set_for_id(set_id: u64, authorities: &[(AuthorityId, AuthorityWeight)]) -> bool {
+		Self::historical_authorities(set_id).map_or(false, |set| &set[..] == authorities)
+	}
+
+	/// Compare a proposed authority set against the current one.
+	///
+	/// Returns the authorities that would be added, those that would be
+	/// removed, and those whose weight would change along with their current
+	/// and proposed weights.
+	pub fn diff_authorities(proposed: &[(AuthorityId, AuthorityWeight)]) -> (
+		Vec<AuthorityId>,
+		Vec<AuthorityId>,
+		Vec<(AuthorityId, AuthorityWeight, AuthorityWeight)>,
+	) {
+		let current = Authorities::get();
+		let weight_in = |set: &[(AuthorityId, AuthorityWeight)], who: &AuthorityId| {
+			set.iter().find(|(id, _)| id == who).map(|(_, weight)| *weight)
+		};
+
+		let mut added = Vec::new();
+		let mut changed = Vec::new();
+		for (id, weight) in proposed {
+			match weight_in(&current, id) {
+				None => added.push(id.clone()),
+				Some(current_weight) if current_weight != *weight =>
+					changed.push((id.clone(), current_weight, *weight)),
+				Some(_) => {},
+			}
+		}
+
+		let removed = current.iter()
+			.filter(|(id, _)| weight_in(proposed, id).is_none())
+			.map(|(id, _)| id.clone())
+			.collect();
+
+		(added, removed, changed)
+	}
+
+	/// Get the total weight of the current authority set, saturating at
+	/// `AuthorityWeight::max_value()`.
+	pub fn total_weight() -> AuthorityWeight {
+		Authorities::get().iter().fold(0, |total: AuthorityWeight, (_, weight)| total.saturating_add(*weight))
+	}
+
+	/// Get the weight the current authority set needs to reach a supermajority,
+	/// i.e. `2/3 * total_weight + 1`.
+	pub fn supermajority_threshold() -> AuthorityWeight {
+		let total = Self::total_weight();
+		// computed piecewise so that `2 * total` cannot overflow.
+		(total / 3 * 2 + total % 3 * 2 / 3).saturating_add(1)
+	}
+
+	/// Get the minimum number of current authorities that must be online for
+	/// GRANDPA to reach a supermajority, i.e. the smallest number of the
+	/// highest-weighted authorities whose combined weight exceeds two thirds
+	/// of the total weight.
+	pub fn min_validators_for_finality() -> u32 {
+		let mut weights = Authorities::get().into_iter().map(|(_, weight)| weight).collect::<Vec<_>>();
+		weights.sort_unstable_by(|a, b| b.cmp(a));
+
+		let threshold = Self::supermajority_threshold();
+
+		let mut accumulated = 0u64;
+		let mut count = 0;
+		for weight in weights {
+			if accumulated >= threshold {
+				break;
+			}
+			accumulated = accumulated.saturating_add(weight);
+			count += 1;
+		}
+
+		count
+	}
+
+	/// Whether finality is stalled and a forced change will be scheduled at the
+	/// next session change.
+	pub fn is_stalled() -> bool {
+		<Stalled<T>>::exists()
+	}
+
+	/// Get the block at which the next queued authority set change will be
+	/// enacted, if any.
+	pub fn pending_change_enactment() -> Option<T::BlockNumber> {
+		<PendingChanges<T>>::get().first().map(|change| change.scheduled_at + change.delay)
+	}
+
+	/// Get the number of blocks from `now` until the next queued authority set
+	/// change is enacted, if any.
+	pub fn blocks_until_change(now: T::BlockNumber) -> Option<T::BlockNumber> {
+		Self::pending_change_enactment().map(|enactment| enactment.saturating_sub(now))
+	}
+
+	/// Schedule GRANDPA to pause starting in the given number of blocks.
+	///
+	/// Cannot be done while an authority set change is pending, since the new
+	/// set would otherwise be enacted on a paused voter.
 	pub fn schedule_pause(in_blocks: T::BlockNumber) -> Result {
+		if <PendingChanges<T>>::exists() {
+			return Err("Cannot signal GRANDPA pause while an authority set change is pending.");
+		}
+
 		if let StoredState::Live = <State<T>>::get() {
 			let scheduled_at = <system::Module<T>>::block_number();
+			if scheduled_at.checked_add(&in_blocks).is_none() {
+				return Err("GRANDPA pause would be enacted past the maximum block number");
+			}
+
 			<State<T>>::put(StoredState::PendingPause {
 				delay: in_blocks,
 				scheduled_at,
 			});
+			ActiveFlags::mutate(|flags| *flags |= PENDING_STATE_FLAG);
 
 			Ok(())
 		} else {
@@ -262,13 +612,22 @@ impl<T: Trait> Module<T> {
 		}
 	}
 
+	/// Schedule a resume of GRANDPA after pausing.
+	///
+	/// Authority set changes can only be scheduled again once the resume has
+	/// been enacted and the set is live.
 	pub fn schedule_resume(in_blocks: T::BlockNumber) -> Result {
 		if let StoredState::Paused = <State<T>>::get() {
 			let scheduled_at = <system::Module<T>>::block_number();
+			if scheduled_at.checked_add(&in_blocks).is_none() {
+				return Err("GRANDPA resume would be enacted past the maximum block number");
+			}
+
 			<State<T>>::put(StoredState::PendingResume {
 				delay: in_blocks,
 				scheduled_at,
 			});
+			ActiveFlags::mutate(|flags| *flags |= PENDING_STATE_FLAG);
 
 			Ok(())
 		} else {
@@ -289,37 +648,156 @@ impl<T: Trait> Module<T> {
 	/// indicates the median last finalized block number and it should be used
 	/// as the canon block when starting the new grandpa voter.
 	///
-	/// No change should be signaled while any change is pending. Returns
-	/// an error if a change is already pending.
+	/// The new set must be non-empty, contain at most `MaxAuthorities`
+	/// distinct authorities and have no zero weights. Weights are scaled down
+	/// if their total would overflow an `AuthorityWeight`.
+	///
+	/// If `ForcedChangeOverridesPending` is set, a forced change drops any
+	/// queued regular changes before being queued itself.
+	///
+	/// Up to `MaxPendingChanges` changes can be queued. A queued change is only
+	/// signaled in the block after the previously queued change is enacted, so
+	/// that no change is signaled while another is pending and enactment blocks
+	/// are strictly increasing. Returns an error if the queue is full.
+	///
+	/// Authority set changes and pauses are mutually exclusive: no change may
+	/// be scheduled unless the set is live (i.e. not paused, pending pause or
+	/// pending resume), and no pause may be scheduled while a change is
+	/// pending. Returns an error otherwise.
 	pub fn schedule_change(
-		next_authorities: Vec<(AuthorityId, u64)>,
+		mut next_authorities: Vec<(AuthorityId, u64)>,
 		in_blocks: T::BlockNumber,
 		forced: Option<T::BlockNumber>,
 	) -> Result {
-		if !<PendingChange<T>>::exists() {
-			let scheduled_at = <system::Module<T>>::block_number();
+		Self::validate_authorities(&next_authorities)?;
+		weights::normalize_weights(&mut next_authorities);
 
-			if let Some(_) = forced {
-				if Self::next_forced().map_or(false, |next| next > scheduled_at) {
-					return Err("Cannot signal forced change so soon after last.");
-				}
+		match <State<T>>::get() {
+			StoredState::Live => {},
+			_ => return Err("Cannot schedule authority change while GRANDPA is not live"),
+		}
+
+		let mut pending_changes = <PendingChanges<T>>::get();
+		let now = <system::Module<T>>::block_number();
+
+		// a forced change may replace queued regular changes that have not been
+		// signaled yet. signaled ones are kept, since native voters may still
+		// enact them, and the forced change is queued behind them.
+		let queued = pending_changes.len();
+		if forced.is_some() && T::ForcedChangeOverridesPending::get() {
+			pending_changes.retain(|change| change.forced.is_some() || change.scheduled_at < now);
+		}
+		let overridden = queued - pending_changes.len();
+
+		if pending_changes.len() >= T::MaxPendingChanges::get() as usize {
+			return Err("Attempt to signal GRANDPA change with the maximum number already pending.");
+		}
+
+		let scheduled_at = pending_changes.last()
+			.map(|last| (last.scheduled_at + last.delay).saturating_add(One::one()))
+			.map_or(now, |after_last| rstd::cmp::max(now, after_last));
 
-				// only allow the next forced change when twice the window has passed since
-				// this one.
-				<NextForced<T>>::put(scheduled_at + in_blocks * 2.into());
+		if scheduled_at.checked_add(&in_blocks).is_none() {
+			return Err("Authority set change would be enacted past the maximum block number");
+		}
+
+		if let Some(median) = forced {
+			if Self::next_forced().map_or(false, |next| next > scheduled_at) {
+				return Err("Cannot signal forced change so soon after last.");
 			}
 
-			<PendingChange<T>>::put(StoredPendingChange {
-				delay: in_blocks,
-				scheduled_at,
-				next_authorities,
-				forced,
-			});
+			// only allow the next forced change when twice the window has passed since
+			// this one.
+			<NextForced<T>>::put(scheduled_at.saturating_add(in_blocks.saturating_mul(2.into())));
+			<LastForcedMedian<T>>::put(median);
+		}
 
-			Ok(())
-		} else {
-			Err("Attempt to signal GRANDPA change with one already pending.")
+		pending_changes.push(StoredPendingChange {
+			delay: in_blocks,
+			scheduled_at,
+			next_authorities,
+			forced,
+		});
+		<PendingChanges<T>>::put(pending_changes);
+		ActiveFlags::mutate(|flags| *flags |= PENDING_CHANGES_FLAG);
+
+		if overridden > 0 {
+			Self::deposit_event(RawEvent::PendingChangesOverridden(overridden as u32));
+		}
+
+		Ok(())
+	}
+
+	/// Run the one-off storage migrations of chains upgraded from an older
+	/// version of this module, and clear `MIGRATIONS_FLAG` once they are done.
+	fn migrate() {
+		let mut flags = Self::active_flags();
+
+		// migrate a change pending under the old single-change storage.
+		if let Some(pending_change) = <PendingChange<T>>::take() {
+			<PendingChanges<T>>::mutate(|changes| changes.insert(0, pending_change));
+			flags |= PENDING_CHANGES_FLAG;
+		}
+
+		// seed the set id on chains that did not track it from genesis. the
+		// current set is never pruned, so it is recorded once tracking started.
+		let mut done = HistoricalAuthorities::exists(Self::current_set_id());
+		if !done {
+			let authorities = Authorities::get();
+			if !authorities.is_empty() {
+				let set_id = T::InitialSetId::get();
+				CurrentSetId::put(set_id);
+				FirstSetId::put(set_id);
+				HistoricalAuthorities::insert(set_id, authorities);
+				done = true;
+			}
+		}
+
+		// unused bits are cleared along with the migrations bit.
+		if done {
+			flags &= PENDING_CHANGES_FLAG | PENDING_STATE_FLAG;
 		}
+		ActiveFlags::put(flags);
+	}
+
+	/// Record the authority set for `set_id`, pruning the oldest set that falls
+	/// out of the history window.
+	fn record_authority_set(
+		set_id: u64,
+		authorities: &[(AuthorityId, AuthorityWeight)],
+		activated_at: T::BlockNumber,
+	) {
+		HistoricalAuthorities::insert_ref(set_id, authorities);
+		<SetIdActivationBlock<T>>::insert(set_id, activated_at);
+
+		let depth = rstd::cmp::max(T::AuthoritySetHistoryDepth::get(), 1);
+		if let Some(pruned) = set_id.checked_sub(depth as u64) {
+			HistoricalAuthorities::remove(pruned);
+			SetIdSession::remove(pruned);
+			<SetIdActivationBlock<T>>::remove(pruned);
+		}
+	}
+
+	/// Check that the given authorities form a valid GRANDPA authority set.
+	fn validate_authorities(authorities: &[(AuthorityId, AuthorityWeight)]) -> Result {
+		if authorities.is_empty() {
+			return Err("Authority set cannot be empty");
+		}
+
+		if authorities.len() > T::MaxAuthorities::get() as usize {
+			return Err("Too many authorities");
+		}
+
+		if authorities.iter().any(|(_, weight)| *weight == 0) {
+			return Err("Authority weights must be non-zero");
+		}
+
+		let mut seen = BTreeSet::new();
+		if !authorities.iter().all(|(id, _)| seen.insert(id)) {
+			return Err("Duplicate authority in authority set");
+		}
+
+		Ok(())
 	}
 
 	/// Deposit one of this module's logs.
@@ -328,10 +806,18 @@ impl<T: Trait> Module<T> {
 		<system::Module<T>>::deposit_log(log.into());
 	}
 
+	/// Initialize the genesis authority set, which always has set id `0`.
 	fn initialize_authorities(authorities: &[(AuthorityId, AuthorityWeight)]) {
 		if !authorities.is_empty() {
 			assert!(Authorities::get().is_empty(), "Authorities are already initialized!");
-			Authorities::put_ref(authorities);
+			// the same validation as for session changes, there is no set to fall back to.
+			Self::validate_authorities(authorities).expect("Invalid genesis authorities");
+			let mut authorities = authorities.to_vec();
+			weights::normalize_weights(&mut authorities);
+
+			Authorities::put(&authorities);
+			CurrentSetId::put(0);
+			Self::record_authority_set(0, &authorities, Zero::zero());
 		}
 	}
 }
@@ -367,7 +853,29 @@ impl<T: Trait> Module<T> {
 	}
 }
 
-impl<T: Trait> session::OneSessionHandler<T::AccountId> for Module<T> {
+impl<T: Trait + session::Trait> Module<T> {
+	/// Record the current session for the current set id, unless the set was
+	/// already seen in an earlier session.
+	fn note_set_id_session() {
+		let set_id = Self::current_set_id();
+		if !SetIdSession::exists(set_id) {
+			SetIdSession::insert(set_id, <session::Module<T>>::current_index());
+		}
+	}
+
+	/// Record the current session for the set id enacted at the end of this
+	/// block by a zero-delay change, since that set already holds the keys of
+	/// the current session.
+	fn note_enacting_set_id_session() {
+		// a queued change is only enacted in this block if nothing is ahead of it.
+		if Self::pending_changes().len() == 1 {
+			let set_id = Self::current_set_id() + 1;
+			SetIdSession::insert(set_id, <session::Module<T>>::current_index());
+		}
+	}
+}
+
+impl<T: Trait + session::Trait> session::OneSessionHandler<T::AccountId> for Module<T> {
 	type Key = AuthorityId;
 
 	fn on_genesis_session<'a, I: 'a>(validators: I)
@@ -375,20 +883,35 @@ impl<T: Trait> session::OneSessionHandler<T::AccountId> for Module<T> {
 	{
 		let authorities = validators.map(|(_, k)| (k, 1)).collect::<Vec<_>>();
 		Self::initialize_authorities(&authorities);
+		Self::note_set_id_session();
 	}
 
 	fn on_new_session<'a, I: 'a>(changed: bool, validators: I, _queued_validators: I)
 		where I: Iterator<Item=(&'a T::AccountId, AuthorityId)>
 	{
+		Self::note_set_id_session();
+
 		// instant changes
 		if changed {
+			// a set failing validation, e.g. one larger than `MaxAuthorities`, is
+			// rejected as a whole and the current set stays in force.
 			let next_authorities = validators.map(|(_, k)| (k, 1)).collect::<Vec<_>>();
 			let last_authorities = <Module<T>>::grandpa_authorities();
 			if next_authorities != last_authorities {
-				if let Some((further_wait, median)) = <Stalled<T>>::take() {
-					let _ = Self::schedule_change(next_authorities, further_wait, Some(median));
+				if let Some((further_wait, median)) = <Stalled<T>>::get() {
+					// keep the stall around if the forced change can't be scheduled
+					// yet (e.g. while paused), so that a later session can retry it.
+					if Self::schedule_change(next_authorities, further_wait, Some(median)).is_ok() {
+						<Stalled<T>>::kill();
+					}
 				} else {
-					let _ = Self::schedule_change(next_authorities, Zero::zero(), None);
+					match Self::schedule_change(next_authorities, Zero::zero(), None) {
+						Ok(()) => Self::note_enacting_set_id_session(),
+						Err(e) => {
+							runtime_io::print(e);
+							Self::deposit_event(RawEvent::SessionChangeRejected);
+						}
+					}
 				}
 			}
 		}
@@ -401,33 +924,40 @@ impl<T: Trait> session::OneSessionHandler<T::AccountId> for Module<T> {
 
 impl<T: Trait> finality_tracker::OnFinalizationStalled<T::BlockNumber> for Module<T> {
 	fn on_stalled(further_wait: T::BlockNumber, median: T::BlockNumber) {
-		// when we record old authority sets, we can use `finality_tracker::median`
-		// to figure out _who_ failed. until then, we can't meaningfully guard
-		// against `next == last` the way that normal session changes do.
-		<Stalled<T>>::put((further_wait, median));
+		// only report the stall once for the same wait and median.
+		if Self::stalled() != Some((further_wait, median)) {
+			// when we record old authority sets, we can use `finality_tracker::median`
+			// to figure out _who_ failed. until then, we can't meaningfully guard
+			// against `next == last` the way that normal session changes do.
+			<Stalled<T>>::put((further_wait, median));
+			Self::deposit_event(RawEvent::Stalled(further_wait, median));
+		}
 	}
 }
 
 /// A round number and set id which point on the time of an offence.
 #[derive(Copy, Clone, PartialOrd, Ord, Eq, PartialEq, Encode, Decode)]
-struct GrandpaTimeSlot {
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct GrandpaTimeSlot {
 	// The order of these matters for `derive(Ord)`.
-	set_id: u64,
-	round: u64,
+	/// Grandpa set id.
+	pub set_id: u64,
+	/// Round number.
+	pub round: u64,
 }
 
 // TODO [slashing]: Integrate this.
-/// A grandpa equivocation offence report.
-#[allow(dead_code)]
-struct GrandpaEquivocationOffence<FullIdentification> {
+/// A grandpa equivocation offence report, to be submitted through the offences
+/// module.
+pub struct GrandpaEquivocationOffence<FullIdentification> {
 	/// Time slot at which this incident happened.
-	time_slot: GrandpaTimeSlot,
+	pub time_slot: GrandpaTimeSlot,
 	/// The session index in which the incident happened.
-	session_index: SessionIndex,
+	pub session_index: SessionIndex,
 	/// The size of the validator set at the time of the offence.
-	validator_set_count: u32,
+	pub validator_set_count: u32,
 	/// The authority which produced this equivocation.
-	offender: FullIdentification,
+	pub offender: FullIdentification,
 }
 
 impl<FullIdentification: Clone> Offence<FullIdentification> for GrandpaEquivocationOffence<FullIdentification> {
@@ -454,9 +984,23 @@ impl<FullIdentification: Clone> Offence<FullIdentification> for GrandpaEquivocat
 		offenders_count: u32,
 		validator_set_count: u32,
 	) -> Perbill {
-		// the formula is min((3k / n)^2, 1)
-		let x = Perbill::from_rational_approximation(3 * offenders_count, validator_set_count);
-		// _ ^ 2
-		x.square()
+		equivocation_slash_fraction(offenders_count.into(), validator_set_count.into())
 	}
 }
+
+/// Compute the slash fraction for a GRANDPA equivocation, given the number (or
+/// total weight) of offending authorities and the size (or total weight) of the
+/// set they belong to.
+///
+/// The formula is `min((3k / n)^2, 1)`, so that a single offender in a large set
+/// is slashed lightly while a third of the set colluding is slashed fully. An
+/// empty set yields a zero fraction.
+pub fn equivocation_slash_fraction(offenders: u64, set_size: u64) -> Perbill {
+	if set_size == 0 {
+		return Perbill::zero();
+	}
+
+	let x = Perbill::from_rational_approximation(offenders.saturating_mul(3), set_size);
+	// _ ^ 2
+	x.square()
+}
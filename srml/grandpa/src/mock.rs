@@ -18,9 +18,10 @@
 
 #![cfg(test)]
 
-use sr_primitives::{Perbill, DigestItem, traits::IdentityLookup, testing::{Header, UintAuthorityId}};
+use std::cell::RefCell;
+use sr_primitives::{Perbill, DigestItem, traits::{IdentityLookup, ConvertInto}, testing::{Header, UintAuthorityId}};
 use runtime_io;
-use srml_support::{impl_outer_origin, impl_outer_event, parameter_types};
+use srml_support::{impl_outer_origin, impl_outer_event, parameter_types, traits::Get};
 use primitives::{H256, Blake2Hasher};
 use codec::{Encode, Decode};
 use crate::{AuthorityId, GenesisConfig, Trait, Module, ConsensusLog};
@@ -34,18 +35,70 @@ pub fn grandpa_log(log: ConsensusLog<u64>) -> DigestItem<H256> {
 	DigestItem::Consensus(GRANDPA_ENGINE_ID, log.encode())
 }
 
+thread_local! {
+	static MAX_PENDING_CHANGES: RefCell<u32> = RefCell::new(1);
+	static FORCED_CHANGE_OVERRIDES_PENDING: RefCell<bool> = RefCell::new(false);
+}
+
+pub struct MaxPendingChanges;
+impl Get<u32> for MaxPendingChanges {
+	fn get() -> u32 { MAX_PENDING_CHANGES.with(|v| *v.borrow()) }
+}
+
+pub struct ForcedChangeOverridesPending;
+impl Get<bool> for ForcedChangeOverridesPending {
+	fn get() -> bool { FORCED_CHANGE_OVERRIDES_PENDING.with(|v| *v.borrow()) }
+}
+
+pub fn set_max_pending_changes(max: u32) {
+	MAX_PENDING_CHANGES.with(|v| *v.borrow_mut() = max);
+}
+
+pub fn set_forced_change_overrides_pending(overrides: bool) {
+	FORCED_CHANGE_OVERRIDES_PENDING.with(|v| *v.borrow_mut() = overrides);
+}
+
 // Workaround for https://github.com/rust-lang/rust/issues/26925 . Remove when sorted.
 #[derive(Clone, PartialEq, Eq, Debug, Decode, Encode)]
 pub struct Test;
-impl Trait for Test {
+parameter_types! {
+	pub const Period: u64 = 1;
+	pub const Offset: u64 = 0;
+}
+
+impl session::Trait for Test {
 	type Event = TestEvent;
+	type ValidatorId = u64;
+	type ValidatorIdOf = ConvertInto;
+	type ShouldEndSession = session::PeriodicSessions<Period, Offset>;
+	type OnSessionEnding = ();
+	type SessionHandler = ();
+	type Keys = UintAuthorityId;
+	type SelectInitialValidators = ();
+}
 
+impl Trait for Test {
+	type Event = TestEvent;
+	type MaxPendingChanges = MaxPendingChanges;
+	type MaxAuthorities = MaxAuthorities;
+	type ForcedChangeOverridesPending = ForcedChangeOverridesPending;
+	type AuthoritySetHistoryDepth = AuthoritySetHistoryDepth;
+	type PauseOrigin = system::EnsureRoot<u64>;
+	type ResumeOrigin = system::EnsureRoot<u64>;
+	type AuthorityRemovalDelay = AuthorityRemovalDelay;
+	type ForceOrigin = system::EnsureRoot<u64>;
+	type CancelOrigin = system::EnsureRoot<u64>;
+	type InitialSetId = InitialSetId;
 }
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
 	pub const MaximumBlockWeight: u32 = 1024;
 	pub const MaximumBlockLength: u32 = 2 * 1024;
 	pub const AvailableBlockRatio: Perbill = Perbill::one();
+	pub const MaxAuthorities: u32 = 10;
+	pub const AuthoritySetHistoryDepth: u32 = 3;
+	pub const AuthorityRemovalDelay: u64 = 2;
+	pub const InitialSetId: u64 = 5;
 }
 impl system::Trait for Test {
 	type Origin = Origin;
@@ -72,7 +125,7 @@ mod grandpa {
 
 impl_outer_event!{
 	pub enum TestEvent for Test {
-		grandpa,
+		grandpa<T>, session,
 	}
 }
 
@@ -83,6 +136,9 @@ pub fn to_authorities(vec: Vec<(u64, u64)>) -> Vec<(AuthorityId, u64)> {
 }
 
 pub fn new_test_ext(authorities: Vec<(u64, u64)>) -> runtime_io::TestExternalities<Blake2Hasher> {
+	set_max_pending_changes(1);
+	set_forced_change_overrides_pending(false);
+
 	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
 	GenesisConfig {
 		authorities: to_authorities(authorities),
@@ -91,4 +147,5 @@ pub fn new_test_ext(authorities: Vec<(u64, u64)>) -> runtime_io::TestExternaliti
 }
 
 pub type System = system::Module<Test>;
+pub type Session = session::Module<Test>;
 pub type Grandpa = Module<Test>;
@@ -19,12 +19,13 @@
 #![cfg(test)]
 
 use sr_primitives::testing::Digest;
-use sr_primitives::traits::{Header, OnFinalize};
+use sr_primitives::traits::{Header, OnFinalize, OnInitialize};
 use runtime_io::with_externalities;
 use crate::mock::*;
 use system::{EventRecord, Phase};
 use codec::{Decode, Encode};
 use fg_primitives::ScheduledChange;
+use srml_support::traits::Get;
 use super::*;
 
 #[test]
@@ -48,7 +49,7 @@ fn authorities_change_logged() {
 		assert_eq!(System::events(), vec![
 			EventRecord {
 				phase: Phase::Finalization,
-				event: Event::NewAuthorities(to_authorities(vec![(4, 1), (5, 1), (6, 1)])).into(),
+				event: RawEvent::NewAuthorities(1, to_authorities(vec![(4, 1), (5, 1), (6, 1)])).into(),
 				topics: vec![],
 			},
 		]);
@@ -81,7 +82,7 @@ fn authorities_change_logged_after_delay() {
 		assert_eq!(System::events(), vec![
 			EventRecord {
 				phase: Phase::Finalization,
-				event: Event::NewAuthorities(to_authorities(vec![(4, 1), (5, 1), (6, 1)])).into(),
+				event: RawEvent::NewAuthorities(1, to_authorities(vec![(4, 1), (5, 1), (6, 1)])).into(),
 				topics: vec![],
 			},
 		]);
@@ -93,21 +94,21 @@ fn cannot_schedule_change_when_one_pending() {
 	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
 		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
 		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 1, None).unwrap();
-		assert!(<PendingChange<Test>>::exists());
+		assert!(<PendingChanges<Test>>::exists());
 		assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_err());
 
 		Grandpa::on_finalize(1);
 		let header = System::finalize();
 
 		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
-		assert!(<PendingChange<Test>>::exists());
+		assert!(<PendingChanges<Test>>::exists());
 		assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_err());
 
 		Grandpa::on_finalize(2);
 		let header = System::finalize();
 
 		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
-		assert!(!<PendingChange<Test>>::exists());
+		assert!(!<PendingChanges<Test>>::exists());
 		assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_ok());
 
 		Grandpa::on_finalize(3);
@@ -115,6 +116,309 @@ fn cannot_schedule_change_when_one_pending() {
 	});
 }
 
+#[test]
+fn forced_change_window_saturates() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		// twice the window overflows the block number.
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), u64::max_value() / 2 + 1, Some(0)).unwrap();
+
+		assert_eq!(Grandpa::next_forced(), Some(u64::max_value()));
+	});
+}
+
+#[test]
+fn cannot_schedule_change_enacted_past_max_block_number() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+
+		assert_eq!(
+			Grandpa::schedule_change(to_authorities(vec![(4, 1)]), u64::max_value(), Some(0)),
+			Err("Authority set change would be enacted past the maximum block number"),
+		);
+		assert!(!<PendingChanges<Test>>::exists());
+		assert_eq!(Grandpa::next_forced(), None);
+
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1)]), u64::max_value() - 1, None).is_ok());
+	});
+}
+
+#[test]
+fn cannot_schedule_change_when_queue_full() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_max_pending_changes(3);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None).unwrap();
+		Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).unwrap();
+		Grandpa::schedule_change(to_authorities(vec![(6, 1)]), 1, None).unwrap();
+		assert!(Grandpa::schedule_change(to_authorities(vec![(7, 1)]), 1, None).is_err());
+		assert_eq!(Grandpa::pending_changes().len(), 3);
+	});
+}
+
+#[test]
+fn queued_changes_are_signaled_after_previous_enacts() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_max_pending_changes(3);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None).unwrap();
+		Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 2, None).unwrap();
+		Grandpa::schedule_change(to_authorities(vec![(6, 1)]), 0, None).unwrap();
+
+		// each change is signaled right after the previous one is enacted.
+		let pending = Grandpa::pending_changes();
+		assert_eq!(
+			pending.iter().map(|c| (c.scheduled_at, c.scheduled_at + c.delay)).collect::<Vec<_>>(),
+			vec![(1, 2), (3, 5), (6, 6)],
+		);
+		assert!(pending.windows(2).all(|w| w[0].scheduled_at + w[0].delay < w[1].scheduled_at + w[1].delay));
+
+		let mut parent_hash = Default::default();
+		let mut logs = Vec::new();
+		let mut enacted = Vec::new();
+		for i in 1..7 {
+			System::initialize(&i, &parent_hash, &Default::default(), &Default::default());
+			Grandpa::on_finalize(i);
+			let header = System::finalize();
+			parent_hash = header.hash();
+
+			logs.push(header.digest.logs.clone());
+			enacted.push((Grandpa::current_set_id(), Grandpa::grandpa_authorities()));
+		}
+
+		let signal = |delay, id| vec![grandpa_log(ConsensusLog::ScheduledChange(
+			ScheduledChange { delay, next_authorities: to_authorities(vec![(id, 1)]) }
+		))];
+		assert_eq!(logs, vec![
+			signal(1, 4),
+			vec![],
+			signal(2, 5),
+			vec![],
+			vec![],
+			signal(0, 6),
+		]);
+		assert_eq!(enacted, vec![
+			(0, to_authorities(vec![(1, 1), (2, 1), (3, 1)])),
+			(1, to_authorities(vec![(4, 1)])),
+			(1, to_authorities(vec![(4, 1)])),
+			(1, to_authorities(vec![(4, 1)])),
+			(2, to_authorities(vec![(5, 1)])),
+			(3, to_authorities(vec![(6, 1)])),
+		]);
+		assert!(!<PendingChanges<Test>>::exists());
+	});
+}
+
+#[test]
+fn change_queued_in_later_block_is_signaled_after_previous_enacts() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_max_pending_changes(2);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 5, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).unwrap();
+		assert_eq!(Grandpa::pending_changes()[1].scheduled_at, 7);
+
+		// once the queue drains, changes are signaled immediately again.
+		Grandpa::on_finalize(2);
+		let mut header = System::finalize();
+		for i in 3..9 {
+			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
+			Grandpa::on_finalize(i);
+			header = System::finalize();
+		}
+
+		assert_eq!(Grandpa::current_set_id(), 2);
+		System::initialize(&9, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(6, 1)]), 1, None).unwrap();
+		assert_eq!(Grandpa::pending_changes()[0].scheduled_at, 9);
+	});
+}
+
+#[test]
+fn set_id_is_seeded_on_upgraded_chain() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		// a chain upgraded from before set ids were tracked.
+		HistoricalAuthorities::remove(0);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::on_initialize(1);
+		assert_eq!(Grandpa::current_set_id(), InitialSetId::get());
+		assert!(Grandpa::verify_set_for_id(InitialSetId::get(), &Grandpa::grandpa_authorities()));
+		assert_eq!(Grandpa::active_flags() & MIGRATIONS_FLAG, 0);
+
+		// sets before the seeded one never existed on chain.
+		assert_eq!(Grandpa::oldest_available_set_id(), InitialSetId::get());
+
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 0, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		assert_eq!(Grandpa::current_set_id(), InitialSetId::get() + 1);
+
+		assert_eq!(Grandpa::oldest_available_set_id(), InitialSetId::get());
+
+		// the seed is only applied once, the migrations are not checked again.
+		HistoricalAuthorities::remove(InitialSetId::get() + 1);
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_initialize(2);
+		assert_eq!(Grandpa::current_set_id(), InitialSetId::get() + 1);
+	});
+}
+
+#[test]
+fn set_id_is_not_seeded_on_chain_tracking_from_genesis() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::on_initialize(1);
+		assert_eq!(Grandpa::current_set_id(), 0);
+		assert_eq!(Grandpa::first_set_id(), 0);
+		assert_eq!(Grandpa::active_flags() & MIGRATIONS_FLAG, 0);
+	});
+}
+
+#[test]
+fn old_pending_change_is_migrated_on_initialize() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		<PendingChange<Test>>::put(StoredPendingChange {
+			scheduled_at: 1,
+			delay: 1,
+			next_authorities: to_authorities(vec![(4, 1)]),
+			forced: None,
+		});
+
+		System::initialize(&2, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::on_initialize(2);
+
+		assert!(!<PendingChange<Test>>::exists());
+		let pending = Grandpa::pending_changes();
+		assert_eq!(pending.len(), 1);
+		assert_eq!(pending[0].next_authorities, to_authorities(vec![(4, 1)]));
+
+		Grandpa::on_finalize(2);
+		let _header = System::finalize();
+		assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1)]));
+	});
+}
+
+#[test]
+fn cannot_schedule_change_with_too_many_authorities() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+
+		let max = MaxAuthorities::get() as u64;
+		let too_many = to_authorities((1..=max + 1).map(|id| (id, 1)).collect());
+		assert_eq!(Grandpa::schedule_change(too_many, 1, None), Err("Too many authorities"));
+		assert!(!<PendingChanges<Test>>::exists());
+
+		let just_enough = to_authorities((1..=max).map(|id| (id, 1)).collect());
+		assert!(Grandpa::schedule_change(just_enough, 1, None).is_ok());
+	});
+}
+
+#[test]
+fn schedule_change_rejects_invalid_authority_sets() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+
+		assert_eq!(
+			Grandpa::schedule_change(vec![], 1, None),
+			Err("Authority set cannot be empty"),
+		);
+		assert_eq!(
+			Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 0)]), 1, None),
+			Err("Authority weights must be non-zero"),
+		);
+		assert_eq!(
+			Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (4, 2)]), 1, None),
+			Err("Duplicate authority in authority set"),
+		);
+		assert!(!<PendingChanges<Test>>::exists());
+
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 2), (6, 3)]), 1, None).is_ok());
+	});
+}
+
+#[test]
+#[should_panic(expected = "Too many authorities")]
+fn genesis_authorities_are_bounded() {
+	let max = MaxAuthorities::get() as u64;
+	new_test_ext((1..=max + 1).map(|id| (id, 1)).collect());
+}
+
+#[test]
+fn forced_change_on_stall_overrides_unsignaled_regular_change() {
+	use session::OneSessionHandler;
+	use finality_tracker::OnFinalizationStalled;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_forced_change_overrides_pending(true);
+		set_max_pending_changes(3);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 10, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		// queued behind the signaled change, so not signaled yet.
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(7, 1)]), 1, None).unwrap();
+		Grandpa::on_stalled(3, 0);
+
+		let accounts = vec![5u64, 6];
+		let validators = accounts.iter()
+			.zip(to_authorities(vec![(5, 1), (6, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		Grandpa::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+
+		// the signaled change is kept and the forced change queued behind it.
+		let pending = Grandpa::pending_changes();
+		assert_eq!(pending.len(), 2);
+		assert_eq!(pending[0].next_authorities, to_authorities(vec![(4, 1)]));
+		assert_eq!(pending[0].forced, None);
+		assert_eq!(pending[1].next_authorities, to_authorities(vec![(5, 1), (6, 1)]));
+		assert_eq!(pending[1].scheduled_at, 12);
+		assert_eq!(pending[1].forced, Some(0));
+		assert_eq!(Grandpa::stalled(), None);
+		assert_eq!(Grandpa::last_forced_median(), Some(0));
+
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::ApplyExtrinsic(0),
+				event: RawEvent::Stalled(3, 0).into(),
+				topics: vec![],
+			},
+			EventRecord {
+				phase: Phase::ApplyExtrinsic(0),
+				event: RawEvent::PendingChangesOverridden(1).into(),
+				topics: vec![],
+			},
+		]);
+	});
+}
+
+#[test]
+fn forced_change_queues_behind_regular_change_without_override() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_max_pending_changes(2);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 10, None).unwrap();
+		Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 3, Some(0)).unwrap();
+
+		let pending = Grandpa::pending_changes();
+		assert_eq!(pending.len(), 2);
+		assert!(pending[0].forced.is_none());
+		assert_eq!(pending[1].scheduled_at, 12);
+		assert!(System::events().is_empty());
+	});
+}
+
 #[test]
 fn new_decodes_from_old() {
 	let old = OldStoredPendingChange {
@@ -123,84 +427,965 @@ fn new_decodes_from_old() {
 		next_authorities: to_authorities(vec![(1, 5), (2, 10), (3, 2)]),
 	};
 
-	let encoded = old.encode();
-	let new = StoredPendingChange::<u32>::decode(&mut &encoded[..]).unwrap();
-	assert!(new.forced.is_none());
-	assert_eq!(new.scheduled_at, old.scheduled_at);
-	assert_eq!(new.delay, old.delay);
-	assert_eq!(new.next_authorities, old.next_authorities);
+	let encoded = old.encode();
+	let new = StoredPendingChange::<u32>::decode(&mut &encoded[..]).unwrap();
+	assert!(new.forced.is_none());
+	assert_eq!(new.scheduled_at, old.scheduled_at);
+	assert_eq!(new.delay, old.delay);
+	assert_eq!(new.next_authorities, old.next_authorities);
+}
+
+#[test]
+fn dispatch_forced_change() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(
+			to_authorities(vec![(4, 1), (5, 1), (6, 1)]),
+			5,
+			Some(0),
+		).unwrap();
+
+		assert!(<PendingChanges<Test>>::exists());
+		assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, Some(0)).is_err());
+
+		Grandpa::on_finalize(1);
+		let mut header = System::finalize();
+
+		for i in 2..7 {
+			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
+			assert!(Grandpa::pending_changes()[0].forced.is_some());
+			assert_eq!(Grandpa::next_forced(), Some(11));
+			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_err());
+			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, Some(0)).is_err());
+
+			Grandpa::on_finalize(i);
+			header = System::finalize();
+		}
+
+		// change has been applied at the end of block 6.
+		// add a normal change.
+		{
+			System::initialize(&7, &header.hash(), &Default::default(), &Default::default());
+			assert!(!<PendingChanges<Test>>::exists());
+			assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
+			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_ok());
+			Grandpa::on_finalize(7);
+			header = System::finalize();
+		}
+
+		// run the normal change.
+		{
+			System::initialize(&8, &header.hash(), &Default::default(), &Default::default());
+			assert!(<PendingChanges<Test>>::exists());
+			assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
+			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_err());
+			Grandpa::on_finalize(8);
+			header = System::finalize();
+		}
+
+		// normal change applied. but we can't apply a new forced change for some
+		// time.
+		for i in 9..11 {
+			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
+			assert!(!<PendingChanges<Test>>::exists());
+			assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(5, 1)]));
+			assert_eq!(Grandpa::next_forced(), Some(11));
+			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1), (6, 1)]), 5, Some(0)).is_err());
+			Grandpa::on_finalize(i);
+			header = System::finalize();
+		}
+
+		{
+			System::initialize(&11, &header.hash(), &Default::default(), &Default::default());
+			assert!(!<PendingChanges<Test>>::exists());
+			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1), (6, 1), (7, 1)]), 5, Some(0)).is_ok());
+			assert_eq!(Grandpa::next_forced(), Some(21));
+			Grandpa::on_finalize(11);
+			header = System::finalize();
+		}
+		let _ = header;
+	});
+}
+
+#[test]
+fn set_id_increments_once_per_enacted_change() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		assert_eq!(Grandpa::current_set_id(), 0);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 1, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		// the change has only been signaled.
+		assert_eq!(Grandpa::current_set_id(), 0);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		assert_eq!(Grandpa::current_set_id(), 1);
+
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 0, None).unwrap();
+		Grandpa::on_finalize(3);
+		let _header = System::finalize();
+
+		assert_eq!(Grandpa::current_set_id(), 2);
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::NewAuthorities(2, to_authorities(vec![(5, 1)])).into(),
+				topics: vec![],
+			},
+		]);
+	});
+}
+
+#[test]
+fn set_id_not_incremented_when_forced_change_signaled() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 2, Some(0)).unwrap();
+		Grandpa::on_finalize(1);
+		let mut header = System::finalize();
+
+		assert_eq!(header.digest, Digest {
+			logs: vec![
+				grandpa_log(ConsensusLog::ForcedChange(
+					0,
+					ScheduledChange { delay: 2, next_authorities: to_authorities(vec![(4, 1), (5, 1), (6, 1)]) }
+				)),
+			],
+		});
+		assert_eq!(Grandpa::current_set_id(), 0);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(2);
+		header = System::finalize();
+		assert_eq!(Grandpa::current_set_id(), 0);
+
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(3);
+		let _header = System::finalize();
+		assert_eq!(Grandpa::current_set_id(), 1);
+	});
+}
+
+#[test]
+fn forced_change_signaled_event_deposited_when_signaled() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 2, Some(0)).unwrap();
+		System::note_finished_extrinsics();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::ForcedChangeSignaled(0, to_authorities(vec![(4, 1), (5, 1), (6, 1)])).into(),
+				topics: vec![],
+			},
+		]);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		System::note_finished_extrinsics();
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+		assert!(System::events().is_empty());
+
+		// only the enactment is observed at the enacting block.
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		System::note_finished_extrinsics();
+		Grandpa::on_finalize(3);
+		let _header = System::finalize();
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::NewAuthorities(1, to_authorities(vec![(4, 1), (5, 1), (6, 1)])).into(),
+				topics: vec![],
+			},
+		]);
+	});
+}
+
+#[test]
+fn remove_authority_schedules_reduced_set() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		let removed = to_authorities(vec![(2, 1)])[0].0.clone();
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::remove_authority(Origin::signed(1), removed.clone()),
+			Err("bad origin: expected to be a root origin"),
+		);
+		assert_eq!(Grandpa::remove_authority(Origin::ROOT, removed.clone()), Ok(()));
+
+		let pending = Grandpa::pending_changes();
+		assert_eq!(pending.len(), 1);
+		assert_eq!(pending[0].next_authorities, to_authorities(vec![(1, 1), (3, 1)]));
+		assert_eq!(pending[0].delay, AuthorityRemovalDelay::get());
+
+		Grandpa::on_finalize(1);
+		let mut header = System::finalize();
+		for i in 2..4 {
+			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
+			Grandpa::on_finalize(i);
+			header = System::finalize();
+		}
+
+		assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(1, 1), (3, 1)]));
+		assert_eq!(
+			Grandpa::remove_authority(Origin::ROOT, removed),
+			Err("Authority is not in the latest scheduled set"),
+		);
+	});
+}
+
+#[test]
+fn remove_authority_applies_to_queued_change() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_max_pending_changes(2);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 1, None).unwrap();
+
+		// authority 2 is already rotated out by the queued change.
+		let current = to_authorities(vec![(2, 1)])[0].0.clone();
+		assert_eq!(
+			Grandpa::remove_authority(Origin::ROOT, current),
+			Err("Authority is not in the latest scheduled set"),
+		);
+
+		let queued = to_authorities(vec![(4, 1)])[0].0.clone();
+		assert_eq!(Grandpa::remove_authority(Origin::ROOT, queued), Ok(()));
+
+		let pending = Grandpa::pending_changes();
+		assert_eq!(pending.len(), 2);
+		assert_eq!(pending[0].next_authorities, to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
+		assert_eq!(pending[1].next_authorities, to_authorities(vec![(5, 1), (6, 1)]));
+	});
+}
+
+#[test]
+fn cannot_remove_last_authority() {
+	with_externalities(&mut new_test_ext(vec![(1, 1)]), || {
+		let only = to_authorities(vec![(1, 1)])[0].0.clone();
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::remove_authority(Origin::ROOT, only),
+			Err("Cannot remove the last authority"),
+		);
+		assert!(!<PendingChanges<Test>>::exists());
+	});
+}
+
+#[test]
+fn pending_change_enactment_is_computed() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(Grandpa::pending_change_enactment(), None);
+		assert_eq!(Grandpa::blocks_until_change(1), None);
+
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 5, None).unwrap();
+
+		assert_eq!(Grandpa::pending_change_enactment(), Some(6));
+		assert_eq!(Grandpa::blocks_until_change(1), Some(5));
+		assert_eq!(Grandpa::blocks_until_change(6), Some(0));
+		assert_eq!(Grandpa::blocks_until_change(10), Some(0));
+	});
+}
+
+#[test]
+fn cancel_scheduled_change_before_signal() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 1, None).unwrap();
+
+		assert_eq!(Grandpa::cancel_scheduled_change(Origin::signed(1)), Err("bad origin: expected to be a root origin"));
+		assert_eq!(Grandpa::cancel_scheduled_change(Origin::ROOT), Ok(()));
+		assert!(!<PendingChanges<Test>>::exists());
+
+		System::note_finished_extrinsics();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		assert_eq!(header.digest, Digest { logs: vec![] });
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::ApplyExtrinsic(0),
+				event: RawEvent::ScheduledChangeCanceled.into(),
+				topics: vec![],
+			},
+		]);
+	});
+}
+
+#[test]
+fn cancel_scheduled_change_after_signal() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 2, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::cancel_scheduled_change(Origin::ROOT),
+			Err("Cannot cancel a change that has already been signaled"),
+		);
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(3);
+		let _header = System::finalize();
+
+		// the signaled change is still enacted, as it is by native voters.
+		assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
+		assert_eq!(Grandpa::current_set_id(), 1);
+	});
+}
+
+#[test]
+fn cancel_scheduled_change_fails_without_pending_change() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::cancel_scheduled_change(Origin::ROOT),
+			Err("No authority set change is pending"),
+		);
+	});
+}
+
+#[test]
+fn cannot_cancel_forced_change() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 2, Some(0)).unwrap();
+
+		// neither before nor after it is signaled.
+		assert_eq!(
+			Grandpa::cancel_scheduled_change(Origin::ROOT),
+			Err("Cannot cancel a forced change"),
+		);
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::cancel_scheduled_change(Origin::ROOT),
+			Err("Cannot cancel a forced change"),
+		);
+		assert!(<PendingChanges<Test>>::exists());
+	});
+}
+
+#[test]
+fn cannot_cancel_queue_with_signaled_regular_change() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		set_max_pending_changes(2);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 2, None).unwrap();
+		Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		// the first change is signaled, the queued one is not yet.
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		let pending = Grandpa::pending_changes();
+		assert!(pending.iter().all(|change| change.forced.is_none()));
+		assert_eq!(
+			Grandpa::cancel_scheduled_change(Origin::ROOT),
+			Err("Cannot cancel a change that has already been signaled"),
+		);
+		assert_eq!(Grandpa::pending_changes().encode(), pending.encode());
+	});
+}
+
+#[test]
+fn pause_and_resume_cannot_overflow_block_number() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::pause(Origin::ROOT, u64::max_value()),
+			Err("GRANDPA pause would be enacted past the maximum block number"),
+		);
+		assert_eq!(Grandpa::state(), StoredState::Live);
+
+		assert_eq!(Grandpa::pause(Origin::ROOT, 0), Ok(()));
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::resume(Origin::ROOT, u64::max_value()),
+			Err("GRANDPA resume would be enacted past the maximum block number"),
+		);
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+		assert!(Grandpa::resume(Origin::ROOT, u64::max_value() - 2).is_ok());
+	});
+}
+
+#[test]
+fn set_id_not_incremented_by_pause_and_resume() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(0).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+		assert_eq!(Grandpa::current_set_id(), 0);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_resume(0).unwrap();
+		Grandpa::on_finalize(2);
+		let _header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Live);
+		assert_eq!(Grandpa::current_set_id(), 0);
+	});
+}
+
+#[test]
+fn set_activation_blocks_are_recorded() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		assert_eq!(Grandpa::set_activation_block(0), Some(0));
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1)]), 2, None).unwrap();
+		Grandpa::on_finalize(1);
+		let mut header = System::finalize();
+
+		for i in 2..6 {
+			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
+			if i == 4 {
+				Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).unwrap();
+			}
+			Grandpa::on_finalize(i);
+			header = System::finalize();
+		}
+
+		assert_eq!(Grandpa::current_set_id(), 2);
+		assert_eq!(Grandpa::set_activation_block(1), Some(3));
+		assert_eq!(Grandpa::set_activation_block(2), Some(5));
+		assert_eq!(Grandpa::set_activation_block(3), None);
+	});
+}
+
+#[test]
+fn authority_set_returns_set_id_with_authorities() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		assert_eq!(Grandpa::authority_set(), (0, to_authorities(vec![(1, 1), (2, 1), (3, 1)])));
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1)]), 0, None).unwrap();
+		Grandpa::on_finalize(1);
+		let _header = System::finalize();
+
+		let (set_id, authorities) = Grandpa::authority_set();
+		assert_eq!(set_id, Grandpa::current_set_id());
+		assert_eq!(authorities, Grandpa::grandpa_authorities());
+		assert_eq!((set_id, authorities), (1, to_authorities(vec![(4, 1), (5, 1)])));
+	});
+}
+
+#[test]
+fn verify_set_for_id_checks_recorded_sets() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		let genesis = to_authorities(vec![(1, 1), (2, 1), (3, 1)]);
+		let first = to_authorities(vec![(4, 1), (5, 1), (6, 1)]);
+		let second = to_authorities(vec![(5, 2)]);
+
+		assert!(Grandpa::verify_set_for_id(0, &genesis));
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(first.clone(), 0, None).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(second.clone(), 0, None).unwrap();
+		Grandpa::on_finalize(2);
+		let _header = System::finalize();
+
+		assert!(Grandpa::verify_set_for_id(0, &genesis));
+		assert!(Grandpa::verify_set_for_id(1, &first));
+		assert!(Grandpa::verify_set_for_id(2, &second));
+
+		// mismatched sets and weights.
+		assert!(!Grandpa::verify_set_for_id(1, &genesis));
+		assert!(!Grandpa::verify_set_for_id(2, &to_authorities(vec![(5, 1)])));
+
+		// unknown set id.
+		assert!(!Grandpa::verify_set_for_id(3, &second));
+	});
+}
+
+#[test]
+fn set_id_session_records_first_session_of_each_set() {
+	use session::OneSessionHandler;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		let accounts = vec![1u64, 2, 3];
+		let validators = accounts.iter()
+			.zip(to_authorities(vec![(1, 1), (2, 1), (3, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		let new_validators = accounts.iter()
+			.zip(to_authorities(vec![(4, 1), (5, 1), (6, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Session::rotate_session();
+		Grandpa::on_new_session(false, validators.clone().into_iter(), validators.clone().into_iter());
+		assert_eq!(Grandpa::session_for_set_id(0), Some(1));
+
+		// the set id is only recorded for the first session it is seen in.
+		Session::rotate_session();
+		Grandpa::on_new_session(false, validators.clone().into_iter(), validators.into_iter());
+		assert_eq!(Grandpa::session_for_set_id(0), Some(1));
+
+		Session::rotate_session();
+		Grandpa::on_new_session(true, new_validators.clone().into_iter(), new_validators.clone().into_iter());
+		Grandpa::on_finalize(1);
+		let _header = System::finalize();
+		// the new set holds the keys of the session it was enacted in.
+		assert_eq!(Grandpa::current_set_id(), 1);
+		assert_eq!(Grandpa::session_for_set_id(1), Some(3));
+
+		System::initialize(&2, &Default::default(), &Default::default(), &Default::default());
+		Session::rotate_session();
+		Grandpa::on_new_session(false, new_validators.clone().into_iter(), new_validators.into_iter());
+		assert_eq!(Grandpa::session_for_set_id(1), Some(3));
+	});
+}
+
+#[test]
+fn historical_authorities_are_pruned_beyond_history_depth() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		let depth = AuthoritySetHistoryDepth::get() as u64;
+		let set_for = |set_id: u64| to_authorities(vec![(set_id + 10, 1)]);
+
+		for set_id in 1..=depth {
+			let block = set_id;
+			System::initialize(&block, &Default::default(), &Default::default(), &Default::default());
+			Grandpa::schedule_change(set_for(set_id), 0, None).unwrap();
+			Grandpa::on_finalize(block);
+			let _header = System::finalize();
+		}
+
+		// the genesis set has just fallen out of the window.
+		assert_eq!(Grandpa::current_set_id(), depth);
+		assert_eq!(Grandpa::historical_authorities(0), None);
+		for set_id in 1..=depth {
+			assert_eq!(Grandpa::historical_authorities(set_id), Some(set_for(set_id)));
+		}
+		assert!(Grandpa::verify_set_for_id(depth, &Grandpa::grandpa_authorities()));
+	});
+}
+
+#[test]
+fn authorities_at_returns_each_retained_set() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		let depth = AuthoritySetHistoryDepth::get() as u64;
+		let set_for = |set_id: u64| to_authorities(vec![(set_id + 10, 1), (set_id + 20, 2)]);
+
+		assert_eq!(Grandpa::oldest_available_set_id(), 0);
+		assert_eq!(Grandpa::authorities_at(0), Some(to_authorities(vec![(1, 1), (2, 1), (3, 1)])));
+
+		let rotations = depth + 2;
+		for set_id in 1..=rotations {
+			let block = set_id;
+			System::initialize(&block, &Default::default(), &Default::default(), &Default::default());
+			Grandpa::schedule_change(set_for(set_id), 0, None).unwrap();
+			Grandpa::on_finalize(block);
+			let _header = System::finalize();
+		}
+
+		let oldest = rotations - depth + 1;
+		assert_eq!(Grandpa::oldest_available_set_id(), oldest);
+		for set_id in 0..oldest {
+			assert_eq!(Grandpa::authorities_at(set_id), None);
+		}
+		for set_id in oldest..=rotations {
+			assert_eq!(Grandpa::authorities_at(set_id), Some(set_for(set_id)));
+		}
+		assert_eq!(Grandpa::authorities_at(rotations + 1), None);
+	});
+}
+
+#[test]
+fn session_set_above_max_authorities_is_rejected() {
+	use session::OneSessionHandler;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+
+		let max = MaxAuthorities::get() as u64;
+		let accounts = (10..10 + max + 5).collect::<Vec<u64>>();
+		let authorities = to_authorities(accounts.iter().map(|id| (*id, 1)).collect());
+		let validators = accounts.iter()
+			.zip(authorities.iter().map(|(id, _)| id.clone()))
+			.collect::<Vec<_>>();
+		Grandpa::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::ApplyExtrinsic(0),
+				event: RawEvent::SessionChangeRejected.into(),
+				topics: vec![],
+			},
+		]);
+		Grandpa::on_finalize(1);
+		let _header = System::finalize();
+
+		assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(1, 1), (2, 1), (3, 1)]));
+		assert_eq!(Grandpa::current_set_id(), 0);
+	});
+}
+
+#[test]
+#[should_panic(expected = "Too many authorities")]
+fn genesis_session_above_max_authorities_panics() {
+	use session::OneSessionHandler;
+
+	with_externalities(&mut new_test_ext(vec![]), || {
+		let max = MaxAuthorities::get() as u64;
+		let accounts = (1..=max + 1).collect::<Vec<u64>>();
+		let validators = accounts.iter()
+			.zip(to_authorities(accounts.iter().map(|id| (*id, 1)).collect()).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		Grandpa::on_genesis_session(validators.into_iter());
+	});
+}
+
+#[test]
+fn rejected_session_set_is_reported() {
+	use session::OneSessionHandler;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(1).unwrap();
+
+		let accounts = vec![4u64, 5];
+		let validators = accounts.iter()
+			.zip(to_authorities(vec![(4, 1), (5, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		Grandpa::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+
+		assert!(Grandpa::pending_changes().is_empty());
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::ApplyExtrinsic(0),
+				event: RawEvent::SessionChangeRejected.into(),
+				topics: vec![],
+			},
+		]);
+	});
+}
+
+#[test]
+fn genesis_session_initializes_authorities() {
+	with_externalities(&mut new_test_ext(vec![]), || {
+		assert!(Grandpa::grandpa_authorities().is_empty());
+
+		let authorities = to_authorities(vec![(1, 1), (2, 1), (3, 1)]);
+		let accounts = vec![1u64, 2, 3];
+		<Grandpa as session::OneSessionHandler<u64>>::on_genesis_session(
+			accounts.iter().zip(authorities.iter().map(|(id, _)| id.clone())),
+		);
+
+		assert_eq!(Grandpa::grandpa_authorities(), authorities);
+		assert_eq!(Grandpa::current_set_id(), 0);
+		assert_eq!(Grandpa::historical_authorities(0), Some(authorities));
+	});
+}
+
+#[test]
+fn current_set_id_matches_current_authorities() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		assert_eq!(
+			Grandpa::historical_authorities(Grandpa::current_set_id()),
+			Some(Grandpa::grandpa_authorities()),
+		);
+
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 0, None).unwrap();
+		Grandpa::on_finalize(1);
+		let _header = System::finalize();
+
+		assert_eq!(Grandpa::current_set_id(), 1);
+		assert_eq!(
+			Grandpa::historical_authorities(Grandpa::current_set_id()),
+			Some(Grandpa::grandpa_authorities()),
+		);
+	});
+}
+
+#[test]
+fn diff_authorities_reports_additions_removals_and_weight_changes() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		let id = |n: u64| to_authorities(vec![(n, 1)])[0].0.clone();
+
+		let (added, removed, changed) = Grandpa::diff_authorities(
+			&to_authorities(vec![(1, 1), (3, 5), (4, 1), (5, 2)]),
+		);
+		assert_eq!(added, vec![id(4), id(5)]);
+		assert_eq!(removed, vec![id(2)]);
+		assert_eq!(changed, vec![(id(3), 1, 5)]);
+
+		let (added, removed, changed) = Grandpa::diff_authorities(&Grandpa::grandpa_authorities());
+		assert!(added.is_empty() && removed.is_empty() && changed.is_empty());
+	});
+}
+
+#[test]
+fn normalize_weights_keeps_weights_that_fit() {
+	let mut authorities = to_authorities(vec![(1, 1), (2, 5), (3, u64::max_value() - 6)]);
+	let expected = authorities.clone();
+	weights::normalize_weights(&mut authorities);
+	assert_eq!(authorities, expected);
+}
+
+#[test]
+fn normalize_weights_prevents_total_overflow() {
+	let big = u64::max_value() / 500;
+	let mut authorities = to_authorities((1..=1000).map(|id| (id, big)).collect());
+	weights::normalize_weights(&mut authorities);
+
+	let total = authorities.iter().map(|(_, weight)| *weight as u128).sum::<u128>();
+	assert!(total <= u64::max_value() as u128);
+	// ratios are preserved.
+	assert!(authorities.iter().all(|(_, weight)| *weight == authorities[0].1));
+	assert!(authorities[0].1 >= big / 3);
+}
+
+#[test]
+fn normalize_weights_never_zeroes_a_weight() {
+	let mut authorities = to_authorities(vec![(1, 1), (2, u64::max_value()), (3, u64::max_value())]);
+	weights::normalize_weights(&mut authorities);
+
+	let weights = authorities.iter().map(|(_, weight)| *weight).collect::<Vec<_>>();
+	assert_eq!(weights[0], 1);
+	assert_eq!(weights[1], weights[2]);
+	assert!(weights.iter().map(|weight| *weight as u128).sum::<u128>() <= u64::max_value() as u128);
+}
+
+#[test]
+fn scheduled_change_weights_are_normalized() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(
+			to_authorities(vec![(4, u64::max_value()), (5, u64::max_value())]),
+			0,
+			None,
+		).unwrap();
+		Grandpa::on_finalize(1);
+		let _header = System::finalize();
+
+		let authorities = Grandpa::grandpa_authorities();
+		assert_eq!(authorities[0].1, authorities[1].1);
+		assert!(Grandpa::total_weight() < u64::max_value());
+	});
+}
+
+#[test]
+fn supermajority_threshold_with_even_total_weight() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 2)]), || {
+		assert_eq!(Grandpa::total_weight(), 4);
+		assert_eq!(Grandpa::supermajority_threshold(), 3);
+	});
+}
+
+#[test]
+fn supermajority_threshold_with_odd_total_weight() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 3), (3, 3)]), || {
+		assert_eq!(Grandpa::total_weight(), 7);
+		assert_eq!(Grandpa::supermajority_threshold(), 5);
+	});
+}
+
+#[test]
+fn total_weight_saturates() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1)]), || {
+		// weights written before normalization was introduced.
+		Authorities::put(to_authorities(vec![(1, u64::max_value()), (2, u64::max_value())]));
+		assert_eq!(Grandpa::total_weight(), u64::max_value());
+		assert_eq!(Grandpa::supermajority_threshold(), u64::max_value() / 3 * 2 + 1);
+	});
+}
+
+#[test]
+fn genesis_weights_are_normalized() {
+	let genesis = vec![(1, u64::max_value()), (2, u64::max_value()), (3, 1)];
+	with_externalities(&mut new_test_ext(genesis.clone()), || {
+		let mut expected = to_authorities(genesis);
+		weights::normalize_weights(&mut expected);
+
+		assert_eq!(Grandpa::grandpa_authorities(), expected);
+		assert_eq!(Grandpa::historical_authorities(0), Some(expected));
+		assert!(Grandpa::grandpa_authorities().iter().all(|(_, weight)| *weight > 0));
+		assert!(Grandpa::total_weight() < u64::max_value());
+	});
+}
+
+#[test]
+fn min_validators_for_finality_with_equal_weights() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1), (4, 1)]), || {
+		assert_eq!(Grandpa::min_validators_for_finality(), 3);
+	});
+
+	with_externalities(&mut new_test_ext((1..=7).map(|id| (id, 1)).collect()), || {
+		assert_eq!(Grandpa::min_validators_for_finality(), 5);
+	});
+}
+
+#[test]
+fn min_validators_for_finality_with_skewed_weights() {
+	with_externalities(&mut new_test_ext(vec![(1, 10), (2, 1), (3, 1), (4, 1)]), || {
+		assert_eq!(Grandpa::min_validators_for_finality(), 1);
+	});
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 3), (3, 1), (4, 5)]), || {
+		assert_eq!(Grandpa::min_validators_for_finality(), 2);
+	});
+}
+
+#[test]
+fn pause_and_resume_through_dispatchables() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::pause(Origin::signed(1), 0),
+			Err("bad origin: expected to be a root origin"),
+		);
+		assert_eq!(Grandpa::pause(Origin::ROOT, 0), Ok(()));
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::resume(Origin::signed(1), 0),
+			Err("bad origin: expected to be a root origin"),
+		);
+		assert_eq!(Grandpa::resume(Origin::ROOT, 0), Ok(()));
+		Grandpa::on_finalize(2);
+		let _header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Live);
+	});
+}
+
+#[test]
+fn pause_and_resume_events_carry_enacting_block() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(1).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		System::note_finished_extrinsics();
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::Paused.into(),
+				topics: vec![],
+			},
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::PausedAt(2).into(),
+				topics: vec![],
+			},
+		]);
+
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_resume(2).unwrap();
+		Grandpa::on_finalize(3);
+		let mut header = System::finalize();
+
+		for i in 4..6 {
+			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
+			System::note_finished_extrinsics();
+			Grandpa::on_finalize(i);
+			header = System::finalize();
+		}
+
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::Resumed.into(),
+				topics: vec![],
+			},
+			EventRecord {
+				phase: Phase::Finalization,
+				event: RawEvent::ResumedAt(5).into(),
+				topics: vec![],
+			},
+		]);
+	});
 }
 
 #[test]
-fn dispatch_forced_change() {
+fn state_transitions_through_all_variants() {
 	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		assert_eq!(Grandpa::state(), StoredState::Live);
+
 		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
-		Grandpa::schedule_change(
-			to_authorities(vec![(4, 1), (5, 1), (6, 1)]),
-			5,
-			Some(0),
-		).unwrap();
+		Grandpa::schedule_pause(1).unwrap();
+		assert_eq!(Grandpa::state(), StoredState::PendingPause { scheduled_at: 1, delay: 1 });
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
 
-		assert!(<PendingChange<Test>>::exists());
-		assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, Some(0)).is_err());
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Paused);
 
-		Grandpa::on_finalize(1);
-		let mut header = System::finalize();
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_resume(1).unwrap();
+		assert_eq!(Grandpa::state(), StoredState::PendingResume { scheduled_at: 3, delay: 1 });
+		Grandpa::on_finalize(3);
+		let header = System::finalize();
 
-		for i in 2..7 {
-			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
-			assert!(<PendingChange<Test>>::get().unwrap().forced.is_some());
-			assert_eq!(Grandpa::next_forced(), Some(11));
-			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_err());
-			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, Some(0)).is_err());
+		System::initialize(&4, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(4);
+		let _header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Live);
+	});
+}
 
-			Grandpa::on_finalize(i);
-			header = System::finalize();
-		}
+#[test]
+fn stored_state_discriminant_round_trips() {
+	use std::convert::TryFrom;
 
-		// change has been applied at the end of block 6.
-		// add a normal change.
-		{
-			System::initialize(&7, &header.hash(), &Default::default(), &Default::default());
-			assert!(!<PendingChange<Test>>::exists());
-			assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
-			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_ok());
-			Grandpa::on_finalize(7);
-			header = System::finalize();
-		}
+	let states = vec![
+		StoredState::Live,
+		StoredState::PendingPause { scheduled_at: 0u64, delay: 0 },
+		StoredState::Paused,
+		StoredState::PendingResume { scheduled_at: 0, delay: 0 },
+	];
 
-		// run the normal change.
-		{
-			System::initialize(&8, &header.hash(), &Default::default(), &Default::default());
-			assert!(<PendingChange<Test>>::exists());
-			assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
-			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1)]), 1, None).is_err());
-			Grandpa::on_finalize(8);
-			header = System::finalize();
-		}
+	for (discriminant, state) in states.into_iter().enumerate() {
+		let discriminant = discriminant as u8;
+		assert_eq!(u8::from(state.clone()), discriminant);
+		assert_eq!(StoredState::<u64>::try_from(discriminant), Ok(state));
+	}
 
-		// normal change applied. but we can't apply a new forced change for some
-		// time.
-		for i in 9..11 {
-			System::initialize(&i, &header.hash(), &Default::default(), &Default::default());
-			assert!(!<PendingChange<Test>>::exists());
-			assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(5, 1)]));
-			assert_eq!(Grandpa::next_forced(), Some(11));
-			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1), (6, 1)]), 5, Some(0)).is_err());
-			Grandpa::on_finalize(i);
-			header = System::finalize();
-		}
+	for discriminant in 4..=u8::max_value() {
+		assert!(StoredState::<u64>::try_from(discriminant).is_err());
+	}
 
-		{
-			System::initialize(&11, &header.hash(), &Default::default(), &Default::default());
-			assert!(!<PendingChange<Test>>::exists());
-			assert!(Grandpa::schedule_change(to_authorities(vec![(5, 1), (6, 1), (7, 1)]), 5, Some(0)).is_ok());
-			assert_eq!(Grandpa::next_forced(), Some(21));
-			Grandpa::on_finalize(11);
-			header = System::finalize();
-		}
-		let _ = header;
-	});
+	// embedded fields are dropped.
+	let pending = StoredState::PendingPause { scheduled_at: 5u64, delay: 2 };
+	assert_eq!(
+		StoredState::<u64>::try_from(u8::from(pending)),
+		Ok(StoredState::PendingPause { scheduled_at: 0, delay: 0 }),
+	);
 }
 
 #[test]
@@ -283,6 +1468,250 @@ fn schedule_resume_only_when_paused() {
 	});
 }
 
+#[test]
+fn cannot_schedule_change_when_paused_or_pending_pause() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(1).unwrap();
+
+		// pending pause
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None).is_err());
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, Some(0)).is_err());
+
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		// paused
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None).is_err());
+		assert!(!<PendingChanges<Test>>::exists());
+
+		// pending resume
+		Grandpa::schedule_resume(1).unwrap();
+		assert_eq!(
+			Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None),
+			Err("Cannot schedule authority change while GRANDPA is not live"),
+		);
+		Grandpa::on_finalize(3);
+		let header = System::finalize();
+
+		System::initialize(&4, &header.hash(), &Default::default(), &Default::default());
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, Some(0)).is_err());
+		Grandpa::on_finalize(4);
+		let header = System::finalize();
+
+		// live again
+		System::initialize(&5, &header.hash(), &Default::default(), &Default::default());
+		assert_eq!(Grandpa::state(), StoredState::Live);
+		assert!(Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 1, None).is_ok());
+	});
+}
+
+#[test]
+fn cannot_schedule_pause_when_change_pending() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 1, None).unwrap();
+
+		assert!(Grandpa::schedule_pause(1).is_err());
+		assert_eq!(Grandpa::state(), StoredState::Live);
+
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		assert!(Grandpa::schedule_pause(1).is_err());
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		// the change has been enacted, pausing is allowed again.
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		assert!(Grandpa::schedule_pause(1).is_ok());
+	});
+}
+
+#[test]
+fn pending_change_enacts_before_pause_can_be_scheduled() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1), (6, 1)]), 1, None).unwrap();
+		assert!(Grandpa::schedule_pause(0).is_err());
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(2);
+		let header = System::finalize();
+
+		// the change was enacted on the live set.
+		assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1), (6, 1)]));
+		assert_eq!(Grandpa::state(), StoredState::Live);
+
+		System::initialize(&3, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(0).unwrap();
+		Grandpa::on_finalize(3);
+		let _header = System::finalize();
+
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+	});
+}
+
+#[test]
+fn last_forced_median_is_recorded_on_stall() {
+	use session::OneSessionHandler;
+	use finality_tracker::OnFinalizationStalled;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(Grandpa::last_forced_median(), None);
+
+		Grandpa::on_stalled(5, 7);
+		// nothing is scheduled until the next session change.
+		assert_eq!(Grandpa::last_forced_median(), None);
+
+		let accounts = vec![4u64, 5];
+		let validators = accounts.iter()
+			.zip(to_authorities(vec![(4, 1), (5, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		Grandpa::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+
+		assert_eq!(Grandpa::pending_changes()[0].forced, Some(7));
+		assert_eq!(Grandpa::last_forced_median(), Some(7));
+	});
+}
+
+#[test]
+fn note_stalled_schedules_forced_change_on_next_session() {
+	use session::OneSessionHandler;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert_eq!(
+			Grandpa::note_stalled(Origin::signed(1), 5, 1),
+			Err("bad origin: expected to be a root origin"),
+		);
+		assert_eq!(Grandpa::note_stalled(Origin::ROOT, 5, 1), Ok(()));
+		assert_eq!(Grandpa::stalled(), Some((5, 1)));
+
+		let accounts = vec![4u64, 5];
+		let validators = accounts.iter()
+			.zip(to_authorities(vec![(4, 1), (5, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		Grandpa::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+
+		let pending = Grandpa::pending_changes();
+		assert_eq!(pending[0].forced, Some(1));
+		assert_eq!(pending[0].delay, 5);
+	});
+}
+
+#[test]
+fn cannot_note_stalled_while_forced_change_pending() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1)]), 5, Some(0)).unwrap();
+
+		assert_eq!(
+			Grandpa::note_stalled(Origin::ROOT, 5, 1),
+			Err("Cannot note a stall while a forced change is pending"),
+		);
+		assert_eq!(Grandpa::stalled(), None);
+	});
+}
+
+#[test]
+fn stall_deposits_event() {
+	use finality_tracker::OnFinalizationStalled;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		assert!(!Grandpa::is_stalled());
+
+		Grandpa::on_stalled(5, 1);
+		assert!(Grandpa::is_stalled());
+		assert_eq!(Grandpa::stalled(), Some((5, 1)));
+
+		// reporting the same stall again does not deposit another event.
+		Grandpa::on_stalled(5, 1);
+		assert_eq!(System::events(), vec![
+			EventRecord {
+				phase: Phase::ApplyExtrinsic(0),
+				event: RawEvent::Stalled(5, 1).into(),
+				topics: vec![],
+			},
+		]);
+	});
+}
+
+#[test]
+fn stall_is_kept_when_session_change_happens_while_paused() {
+	use session::OneSessionHandler;
+	use finality_tracker::OnFinalizationStalled;
+
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(0).unwrap();
+		Grandpa::on_finalize(1);
+		let header = System::finalize();
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+
+		System::initialize(&2, &header.hash(), &Default::default(), &Default::default());
+		Grandpa::on_stalled(5, 1);
+
+		let accounts = vec![4u64, 5];
+		let validators = accounts.iter()
+			.zip(to_authorities(vec![(4, 1), (5, 1)]).into_iter().map(|(id, _)| id))
+			.collect::<Vec<_>>();
+		Grandpa::on_new_session(true, validators.clone().into_iter(), validators.into_iter());
+
+		// no change was scheduled on the paused set and the stall is retained.
+		assert!(!<PendingChanges<Test>>::exists());
+		assert_eq!(Grandpa::stalled(), Some((5, 1)));
+	});
+}
+
+#[test]
+fn equivocation_offence_reports_its_offender() {
+	let time_slot = GrandpaTimeSlot { set_id: 2, round: 7 };
+	let offence = GrandpaEquivocationOffence {
+		time_slot,
+		session_index: 3,
+		validator_set_count: 10,
+		offender: 42u64,
+	};
+
+	assert_eq!(offence.offenders(), vec![42]);
+	assert_eq!(offence.session_index(), 3);
+	assert_eq!(offence.validator_set_count(), 10);
+	assert_eq!(offence.time_slot(), time_slot);
+}
+
+#[test]
+fn equivocation_offence_slash_fraction_matches_babe() {
+	// (offenders, validators, expected parts per billion) following the curve
+	// used for BABE equivocations: min((3k / n)^2, 1)
+	const TABLE: &[(u32, u32, u32)] = &[
+		(1, 10, 90_000_000),
+		(2, 10, 360_000_000),
+		(1, 100, 900_000),
+		(3, 9, 1_000_000_000),
+		(5, 10, 1_000_000_000),
+	];
+
+	for &(offenders, validators, expected) in TABLE {
+		assert_eq!(
+			<GrandpaEquivocationOffence<u64> as Offence<u64>>::slash_fraction(offenders, validators),
+			Perbill::from_parts(expected),
+			"offenders: {}, validators: {}", offenders, validators,
+		);
+	}
+}
+
 #[test]
 fn time_slot_have_sane_ord() {
 	// Ensure that `Ord` implementation is sane.
@@ -310,3 +1739,89 @@ fn time_slot_have_sane_ord() {
 	];
 	assert!(FIXTURE.windows(2).all(|f| f[0] < f[1]));
 }
+
+#[test]
+fn equivocation_slash_fraction_follows_curve() {
+	const TABLE: &[(u64, u64, u32)] = &[
+		// (offenders, set size, expected parts per billion)
+		(0, 10, 0),
+		(1, 100, 900_000),
+		(1, 10, 90_000_000),
+		(2, 9, 444_444_443),
+		(3, 9, 1_000_000_000),
+		(10, 10, 1_000_000_000),
+		// more offenders than the set size.
+		(20, 10, 1_000_000_000),
+		// empty set.
+		(0, 0, 0),
+		(5, 0, 0),
+		// no overflow on huge inputs.
+		(u64::max_value(), 1, 1_000_000_000),
+		(u64::max_value(), u64::max_value(), 1_000_000_000),
+		(1, u64::max_value(), 0),
+	];
+
+	for &(offenders, set_size, expected) in TABLE {
+		assert_eq!(
+			equivocation_slash_fraction(offenders, set_size),
+			Perbill::from_parts(expected),
+			"offenders: {}, set size: {}", offenders, set_size,
+		);
+	}
+}
+
+#[test]
+fn active_flags_track_pending_work() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		// everything is checked once, then idle blocks clear the flags.
+		assert_eq!(Grandpa::active_flags(), u8::max_value());
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::on_initialize(1);
+		assert_eq!(Grandpa::active_flags(), PENDING_CHANGES_FLAG | PENDING_STATE_FLAG);
+		Grandpa::on_finalize(1);
+		assert_eq!(Grandpa::active_flags(), 0);
+
+		System::initialize(&2, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1)]), 1, None).unwrap();
+		assert_eq!(Grandpa::active_flags(), PENDING_CHANGES_FLAG);
+		Grandpa::on_finalize(2);
+		assert_eq!(Grandpa::active_flags(), PENDING_CHANGES_FLAG);
+
+		// the change is enacted and nothing else is pending.
+		System::initialize(&3, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(3);
+		assert_eq!(Grandpa::grandpa_authorities(), to_authorities(vec![(4, 1), (5, 1)]));
+		assert_eq!(Grandpa::active_flags(), 0);
+
+		// a pause can only be scheduled once no change is pending.
+		System::initialize(&4, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_pause(1).unwrap();
+		assert_eq!(Grandpa::active_flags(), PENDING_STATE_FLAG);
+		Grandpa::on_finalize(4);
+		assert_eq!(Grandpa::active_flags(), PENDING_STATE_FLAG);
+
+		System::initialize(&5, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::on_finalize(5);
+		assert_eq!(Grandpa::state(), StoredState::Paused);
+		assert_eq!(Grandpa::active_flags(), 0);
+
+		System::initialize(&6, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_resume(0).unwrap();
+		assert_eq!(Grandpa::active_flags(), PENDING_STATE_FLAG);
+		Grandpa::on_finalize(6);
+		assert_eq!(Grandpa::state(), StoredState::Live);
+		assert_eq!(Grandpa::active_flags(), 0);
+	});
+}
+
+#[test]
+fn cancelling_changes_clears_active_flag() {
+	with_externalities(&mut new_test_ext(vec![(1, 1), (2, 1), (3, 1)]), || {
+		System::initialize(&1, &Default::default(), &Default::default(), &Default::default());
+		Grandpa::schedule_change(to_authorities(vec![(4, 1), (5, 1)]), 5, None).unwrap();
+		assert_eq!(Grandpa::active_flags() & PENDING_CHANGES_FLAG, PENDING_CHANGES_FLAG);
+
+		assert_eq!(Grandpa::cancel_scheduled_change(Origin::ROOT), Ok(()));
+		assert_eq!(Grandpa::active_flags() & PENDING_CHANGES_FLAG, 0);
+	});
+}
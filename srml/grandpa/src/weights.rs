@@ -0,0 +1,44 @@
+// Copyright 2017-2019 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers for GRANDPA authority weights.
+
+use rstd::prelude::*;
+use crate::{AuthorityId, AuthorityWeight};
+
+/// Scale the given authority weights down so that their total fits in an
+/// `AuthorityWeight`, preserving their relative ratios as closely as integer
+/// division allows.
+///
+/// Weights are left untouched if their total already fits. Non-zero weights
+/// never become zero.
+pub fn normalize_weights(authorities: &mut Vec<(AuthorityId, AuthorityWeight)>) {
+	let max = AuthorityWeight::max_value() as u128;
+	let total = authorities.iter().map(|(_, weight)| *weight as u128).sum::<u128>();
+	if total <= max {
+		return;
+	}
+
+	// every weight may be rounded up to one, so leave room for that in the total.
+	let room = max - authorities.len() as u128;
+	let divisor = (total + room - 1) / room;
+
+	for (_, weight) in authorities.iter_mut() {
+		if *weight != 0 {
+			*weight = rstd::cmp::max((*weight as u128 / divisor) as AuthorityWeight, 1);
+		}
+	}
+}